@@ -0,0 +1,417 @@
+// http://gbdev.gg8.se/wiki/articles/Gameboy_sound_hardware
+mod noise;
+mod square;
+mod wave;
+
+use self::noise::Noise;
+use self::square::Square;
+use self::wave::Wave;
+
+// The APU is clocked at this rate in T-cycles regardless of CGB double speed.
+pub const CLOCK_RATE: u32 = 4_194_304;
+
+// Bits that always read back as 1 in NR10-NR52, many of them write-only.
+const READ_MASKS: [u8; 0x17] = [
+    0x80, 0x3F, 0x00, 0xFF, 0xBF,
+    0xFF, 0x3F, 0x00, 0xFF, 0xBF,
+    0x7F, 0xFF, 0x9F, 0xFF, 0xBF,
+    0xFF, 0xFF, 0x00, 0x00, 0xBF,
+    0x00, 0x00, 0x70,
+];
+
+// Counts down from 64 (256 for the wave channel) at 256 Hz and switches
+// the channel off when it reaches zero, if enabled in NRx4.
+struct Length {
+    counter: u16,
+    maximum: u16,
+    enabled: bool,
+}
+
+impl Length {
+    fn new(maximum: u16) -> Length {
+        Length {
+            counter: 0,
+            maximum,
+            enabled: false,
+        }
+    }
+
+    fn load(&mut self, value: u8) {
+        self.counter = self.maximum - value as u16;
+    }
+
+    fn trigger(&mut self) {
+        if self.counter == 0 { self.counter = self.maximum }
+    }
+
+    // Returns true when the counter expires.
+    fn clock(&mut self) -> bool {
+        if !self.enabled || self.counter == 0 { return false }
+        self.counter -= 1;
+        self.counter == 0
+    }
+}
+
+// NRx2: a starting volume stepped up or down every period/64 seconds. The
+// top five bits being zero switches the channel's DAC off.
+struct Envelope {
+    initial_volume: u8,
+    increase: bool,
+    period: u8,
+    volume: u8,
+    timer: u8,
+}
+
+impl Envelope {
+    fn new() -> Envelope {
+        Envelope {
+            initial_volume: 0,
+            increase: false,
+            period: 0,
+            volume: 0,
+            timer: 0,
+        }
+    }
+
+    fn dac_enabled(&self) -> bool {
+        self.initial_volume != 0 || self.increase
+    }
+
+    fn write(&mut self, value: u8) {
+        self.initial_volume = value >> 4;
+        self.increase = value & 0x08 != 0;
+        self.period = value & 0x07;
+    }
+
+    fn trigger(&mut self) {
+        self.volume = self.initial_volume;
+        self.timer = self.period;
+    }
+
+    fn clock(&mut self) {
+        if self.period == 0 { return }
+
+        if self.timer > 0 { self.timer -= 1 }
+        if self.timer > 0 { return }
+
+        self.timer = self.period;
+        if self.increase && self.volume < 15 {
+            self.volume += 1;
+        } else if !self.increase && self.volume > 0 {
+            self.volume -= 1;
+        }
+    }
+}
+
+pub struct Apu {
+    registers: [u8; 0x17],
+    square1: Square,
+    square2: Square,
+    wave: Wave,
+    noise: Noise,
+    powered: bool,
+    frame_step: u8,
+    sample_rate: Option<u32>,
+    sample_clock: u32,
+    charge_factor: f32,
+    capacitors: [f32; 2],
+    samples: Vec<i16>,
+    channel_capture: bool,
+    channel_capacitors: [f32; 4],
+    channel_samples: [Vec<i16>; 4],
+}
+
+impl Apu {
+    pub fn new() -> Apu {
+        Apu {
+            registers: [0; 0x17],
+            square1: Square::new(true),
+            square2: Square::new(false),
+            wave: Wave::new(),
+            noise: Noise::new(),
+            powered: false,
+            frame_step: 0,
+            sample_rate: None,
+            sample_clock: 0,
+            charge_factor: 1.0,
+            capacitors: [0.0; 2],
+            samples: Vec::new(),
+            channel_capture: false,
+            channel_capacitors: [0.0; 4],
+            channel_samples: [Vec::new(), Vec::new(), Vec::new(), Vec::new()],
+        }
+    }
+
+    // Starts producing interleaved left/right samples at the given rate.
+    // Until then nothing is buffered, so frontends without audio don't have
+    // to drain it.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = Some(sample_rate);
+        self.sample_clock = 0;
+
+        // The high-pass filter that removes the DAC's DC offset.
+        self.charge_factor = 0.999_958f32.powf(CLOCK_RATE as f32 / sample_rate as f32);
+    }
+
+    pub fn take_samples(&mut self) -> Vec<i16> {
+        ::std::mem::take(&mut self.samples)
+    }
+
+    // Also buffers each channel on its own, as mono samples taken before
+    // panning and master volume, to tell which channel misbehaves.
+    pub fn set_channel_capture(&mut self, enabled: bool) {
+        self.channel_capture = enabled;
+    }
+
+    pub fn take_channel_samples(&mut self) -> [Vec<i16>; 4] {
+        ::std::mem::take(&mut self.channel_samples)
+    }
+
+    pub fn read(&self, address: u16) -> u8 {
+        match address {
+            0xFF26 => self.read_nr52(),
+            0xFF10..=0xFF25 => {
+                let index = address as usize - 0xFF10;
+                self.registers[index] | READ_MASKS[index]
+            },
+            0xFF30..=0xFF3F => self.wave.read_ram(address),
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0xFF26 => self.write_nr52(value),
+            0xFF30..=0xFF3F => self.wave.write_ram(address, value),
+
+            // Everything else is read-only while the APU is off.
+            _ if !self.powered => (),
+
+            0xFF10..=0xFF25 => {
+                self.registers[address as usize - 0xFF10] = value;
+                match address {
+                    0xFF10..=0xFF14 => self.square1.write(address - 0xFF10, value),
+                    0xFF16..=0xFF19 => self.square2.write(address - 0xFF15, value),
+                    0xFF1A..=0xFF1E => self.wave.write(address - 0xFF1A, value),
+                    0xFF20..=0xFF23 => self.noise.write(address - 0xFF1F, value),
+                    _ => (),
+                }
+            },
+            _ => (),
+        }
+    }
+
+    // Advances the channels by a number of T-cycles; four per machine cycle
+    // at normal speed and two in double speed mode.
+    pub fn tick(&mut self, cycles: u32) {
+        for _ in 0..cycles {
+            if self.powered {
+                self.square1.tick();
+                self.square2.tick();
+                self.wave.tick();
+                self.noise.tick();
+            }
+
+            if let Some(sample_rate) = self.sample_rate {
+                self.sample_clock += sample_rate;
+                if self.sample_clock >= CLOCK_RATE {
+                    self.sample_clock -= CLOCK_RATE;
+                    self.push_sample();
+                }
+            }
+        }
+    }
+
+    // Clocked at 512 Hz by a falling edge of DIV bit 4 (bit 5 in double
+    // speed). Lengths run on even steps, the sweep on steps 2 and 6 and the
+    // envelopes on step 7.
+    pub fn step_frame_sequencer(&mut self) {
+        if !self.powered { return }
+
+        if self.frame_step & 0x01 == 0 {
+            self.square1.clock_length();
+            self.square2.clock_length();
+            self.wave.clock_length();
+            self.noise.clock_length();
+        }
+
+        if self.frame_step == 2 || self.frame_step == 6 {
+            self.square1.clock_sweep();
+        }
+
+        if self.frame_step == 7 {
+            self.square1.clock_envelope();
+            self.square2.clock_envelope();
+            self.noise.clock_envelope();
+        }
+
+        self.frame_step = (self.frame_step + 1) % 8;
+    }
+
+    // -------------------------------------------------------------------------
+
+    // Each DAC turns a digital 0-15 into -1.0 to 1.0. NR51 routes channels to
+    // either side, NR50 scales each side by 1/8 to 8/8, and a high-pass
+    // filter removes the DC offset, much like the capacitor on the real
+    // hardware's output.
+    fn push_sample(&mut self) {
+        let outputs = [
+            self.square1.output(),
+            self.square2.output(),
+            self.wave.output(),
+            self.noise.output(),
+        ];
+
+        let nr50 = self.registers[0x14];
+        let nr51 = self.registers[0x15];
+        let mut mix = [0.0f32; 2];
+        let mut any_dac = false;
+
+        for (channel, output) in outputs.iter().enumerate() {
+            let analog = match *output {
+                Some(digital) => digital as f32 / 7.5 - 1.0,
+                None => continue,
+            };
+            any_dac = true;
+
+            if nr51 & (0x10 << channel) != 0 { mix[0] += analog }
+            if nr51 & (0x01 << channel) != 0 { mix[1] += analog }
+        }
+
+        let volumes = [(nr50 >> 4) & 0x07, nr50 & 0x07];
+        for side in 0..2 {
+            let input = mix[side] / 4.0 * (volumes[side] + 1) as f32 / 8.0;
+            let mut output = 0.0;
+
+            if any_dac {
+                output = input - self.capacitors[side];
+                self.capacitors[side] = input - output * self.charge_factor;
+            }
+
+            self.samples.push((output * i16::MAX as f32) as i16);
+        }
+
+        if self.channel_capture {
+            for (channel, output) in outputs.iter().enumerate() {
+                let analog = output.map_or(0.0, |digital| digital as f32 / 7.5 - 1.0);
+                let output = analog - self.channel_capacitors[channel];
+                self.channel_capacitors[channel] = analog - output * self.charge_factor;
+                // Halved, as the filter lets a full swing overshoot to 2.0.
+                self.channel_samples[channel].push((output / 2.0 * i16::MAX as f32) as i16);
+            }
+        }
+    }
+
+    fn read_nr52(&self) -> u8 {
+        let mut value = READ_MASKS[0x16];
+        if self.powered          { value |= 0x80 }
+        if self.square1.enabled() { value |= 0x01 }
+        if self.square2.enabled() { value |= 0x02 }
+        if self.wave.enabled()    { value |= 0x04 }
+        if self.noise.enabled()   { value |= 0x08 }
+        value
+    }
+
+    // Switching the APU off clears every register; switching it back on
+    // restarts the frame sequencer.
+    fn write_nr52(&mut self, value: u8) {
+        let powered = value & 0x80 != 0;
+
+        if self.powered && !powered {
+            self.registers = [0; 0x17];
+            self.square1 = Square::new(true);
+            self.square2 = Square::new(false);
+            self.wave.reset();
+            self.noise = Noise::new();
+        } else if !self.powered && powered {
+            self.frame_step = 0;
+        }
+
+        self.powered = powered;
+    }
+}
+
+impl Default for Apu {
+    fn default() -> Apu {
+        Apu::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn powered_apu() -> Apu {
+        let mut apu = Apu::new();
+        apu.write(0xFF26, 0x80);
+        apu
+    }
+
+    fn step_frame_sequencer(apu: &mut Apu, steps: u32) {
+        for _ in 0..steps {
+            apu.step_frame_sequencer();
+        }
+    }
+
+    #[test]
+    fn length_counter_silences_channel_on_even_steps() {
+        let mut apu = powered_apu();
+        apu.write(0xFF16, 62);
+        apu.write(0xFF17, 0xF0);
+        apu.write(0xFF19, 0xC0);
+        assert_eq!(apu.read(0xFF26) & 0x02, 0x02);
+
+        step_frame_sequencer(&mut apu, 2);
+        assert_eq!(apu.read(0xFF26) & 0x02, 0x02);
+
+        step_frame_sequencer(&mut apu, 1);
+        assert_eq!(apu.read(0xFF26) & 0x02, 0);
+    }
+
+    #[test]
+    fn envelope_steps_volume_on_step_7() {
+        let mut apu = powered_apu();
+        // Duty 2 is high on its first step, so the output is the volume.
+        apu.write(0xFF16, 0x80);
+        apu.write(0xFF17, 0xA1);
+        apu.write(0xFF19, 0x80);
+        assert_eq!(apu.square2.output(), Some(10));
+
+        step_frame_sequencer(&mut apu, 7);
+        assert_eq!(apu.square2.output(), Some(10));
+
+        step_frame_sequencer(&mut apu, 1);
+        assert_eq!(apu.square2.output(), Some(9));
+
+        step_frame_sequencer(&mut apu, 8);
+        assert_eq!(apu.square2.output(), Some(8));
+    }
+
+    #[test]
+    fn sweep_overflow_disables_channel_1_on_step_2() {
+        let mut apu = powered_apu();
+        // Frequency 1200 with a shift of 1 sweeps to 1800, after which the
+        // next step would pass 2047.
+        apu.write(0xFF10, 0x11);
+        apu.write(0xFF12, 0xF0);
+        apu.write(0xFF13, 0xB0);
+        apu.write(0xFF14, 0x84);
+        assert_eq!(apu.read(0xFF26) & 0x01, 0x01);
+
+        step_frame_sequencer(&mut apu, 2);
+        assert_eq!(apu.read(0xFF26) & 0x01, 0x01);
+
+        step_frame_sequencer(&mut apu, 1);
+        assert_eq!(apu.read(0xFF26) & 0x01, 0);
+    }
+
+    #[test]
+    fn registers_are_read_only_while_powered_off() {
+        let mut apu = Apu::new();
+        apu.write(0xFF24, 0x77);
+        apu.write(0xFF30, 0x12);
+        assert_eq!(apu.read(0xFF24), 0x00);
+        assert_eq!(apu.read(0xFF30), 0x12);
+        assert_eq!(apu.read(0xFF26), 0x70);
+    }
+}
@@ -0,0 +1,97 @@
+use super::{Envelope, Length};
+
+const DIVISORS: [u32; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
+
+// Channel 4: pseudo-random noise from a 15-bit linear feedback shift
+// register, optionally shortened to 7 bits for a more tonal sound.
+pub struct Noise {
+    length: Length,
+    envelope: Envelope,
+    enabled: bool,
+    shift: u8,
+    short_mode: bool,
+    divisor_code: u8,
+    timer: u32,
+    lfsr: u16,
+}
+
+impl Noise {
+    pub fn new() -> Noise {
+        Noise {
+            length: Length::new(64),
+            envelope: Envelope::new(),
+            enabled: false,
+            shift: 0,
+            short_mode: false,
+            divisor_code: 0,
+            timer: 0,
+            lfsr: 0x7FFF,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn output(&self) -> Option<u8> {
+        if !self.envelope.dac_enabled() { return None }
+        if !self.enabled { return Some(0) }
+
+        Some(if self.lfsr & 0x01 == 0 { self.envelope.volume } else { 0 })
+    }
+
+    // Writes NR41-NR44, numbered 1-4.
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            1 => self.length.load(value & 0x3F),
+            2 => {
+                self.envelope.write(value);
+                if !self.envelope.dac_enabled() { self.enabled = false }
+            },
+            3 => {
+                self.shift = value >> 4;
+                self.short_mode = value & 0x08 != 0;
+                self.divisor_code = value & 0x07;
+            },
+            _ => {
+                self.length.enabled = value & 0x40 != 0;
+                if value & 0x80 != 0 { self.trigger() }
+            },
+        }
+    }
+
+    pub fn tick(&mut self) {
+        if self.timer > 0 { self.timer -= 1 }
+        if self.timer > 0 { return }
+
+        self.timer = self.period();
+
+        let feedback = (self.lfsr ^ (self.lfsr >> 1)) & 0x01;
+        self.lfsr = (self.lfsr >> 1) | (feedback << 14);
+        if self.short_mode {
+            self.lfsr = (self.lfsr & !0x40) | (feedback << 6);
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() { self.enabled = false }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    // -------------------------------------------------------------------------
+
+    fn period(&self) -> u32 {
+        DIVISORS[self.divisor_code as usize] << self.shift
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger();
+        self.envelope.trigger();
+        self.timer = self.period();
+        self.lfsr = 0x7FFF;
+    }
+}
@@ -0,0 +1,167 @@
+use super::{Envelope, Length};
+
+const DUTY_CYCLES: [u8; 4] = [0b0000_0001, 0b1000_0001, 0b1000_0111, 0b0111_1110];
+
+// Channel 1's frequency sweep. The shadow frequency is recalculated every
+// sweep period and written back, and overflowing past 2047 silences the
+// channel.
+struct Sweep {
+    period: u8,
+    negate: bool,
+    shift: u8,
+    timer: u8,
+    enabled: bool,
+    shadow: u16,
+}
+
+impl Sweep {
+    fn new() -> Sweep {
+        Sweep {
+            period: 0,
+            negate: false,
+            shift: 0,
+            timer: 0,
+            enabled: false,
+            shadow: 0,
+        }
+    }
+
+    fn next_frequency(&self) -> u16 {
+        let delta = self.shadow >> self.shift;
+        if self.negate { self.shadow - delta } else { self.shadow + delta }
+    }
+
+    fn reload_timer(&mut self) {
+        self.timer = if self.period == 0 { 8 } else { self.period };
+    }
+}
+
+// Channels 1 and 2: a square wave with one of four duty cycles.
+pub struct Square {
+    sweep: Option<Sweep>,
+    length: Length,
+    envelope: Envelope,
+    enabled: bool,
+    duty: u8,
+    duty_step: u8,
+    frequency: u16,
+    timer: u16,
+}
+
+impl Square {
+    pub fn new(with_sweep: bool) -> Square {
+        Square {
+            sweep: if with_sweep { Some(Sweep::new()) } else { None },
+            length: Length::new(64),
+            envelope: Envelope::new(),
+            enabled: false,
+            duty: 0,
+            duty_step: 0,
+            frequency: 0,
+            timer: 0,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // The digital output from 0 to 15, or None while the DAC is off.
+    pub fn output(&self) -> Option<u8> {
+        if !self.envelope.dac_enabled() { return None }
+        if !self.enabled { return Some(0) }
+
+        let high = DUTY_CYCLES[self.duty as usize] & (0x80 >> self.duty_step) != 0;
+        Some(if high { self.envelope.volume } else { 0 })
+    }
+
+    // Writes NRx0-NRx4, numbered 0-4.
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => if let Some(ref mut sweep) = self.sweep {
+                sweep.period = (value >> 4) & 0x07;
+                sweep.negate = value & 0x08 != 0;
+                sweep.shift = value & 0x07;
+            },
+            1 => {
+                self.duty = value >> 6;
+                self.length.load(value & 0x3F);
+            },
+            2 => {
+                self.envelope.write(value);
+                if !self.envelope.dac_enabled() { self.enabled = false }
+            },
+            3 => self.frequency = (self.frequency & 0x0700) | value as u16,
+            _ => {
+                self.frequency = (self.frequency & 0x00FF) | ((value as u16 & 0x07) << 8);
+                self.length.enabled = value & 0x40 != 0;
+                if value & 0x80 != 0 { self.trigger() }
+            },
+        }
+    }
+
+    // Advances the frequency timer by one T-cycle.
+    pub fn tick(&mut self) {
+        if self.timer > 0 { self.timer -= 1 }
+        if self.timer == 0 {
+            self.timer = self.period();
+            self.duty_step = (self.duty_step + 1) % 8;
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() { self.enabled = false }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_sweep(&mut self) {
+        let mut disable = false;
+
+        if let Some(ref mut sweep) = self.sweep {
+            if sweep.timer > 0 { sweep.timer -= 1 }
+            if sweep.timer > 0 { return }
+
+            sweep.reload_timer();
+            if !sweep.enabled || sweep.period == 0 { return }
+
+            let frequency = sweep.next_frequency();
+            if frequency > 2047 {
+                disable = true;
+            } else if sweep.shift != 0 {
+                sweep.shadow = frequency;
+                self.frequency = frequency;
+                disable = sweep.next_frequency() > 2047;
+            }
+        }
+
+        if disable { self.enabled = false }
+    }
+
+    // -------------------------------------------------------------------------
+
+    fn period(&self) -> u16 {
+        (2048 - self.frequency) * 4
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger();
+        self.envelope.trigger();
+        self.timer = self.period();
+
+        let frequency = self.frequency;
+        let mut disable = false;
+
+        if let Some(ref mut sweep) = self.sweep {
+            sweep.shadow = frequency;
+            sweep.reload_timer();
+            sweep.enabled = sweep.period != 0 || sweep.shift != 0;
+            disable = sweep.shift != 0 && sweep.next_frequency() > 2047;
+        }
+
+        if disable { self.enabled = false }
+    }
+}
@@ -0,0 +1,100 @@
+use super::Length;
+
+// Channel 3: plays back 32 four-bit samples from wave RAM (0xFF30-0xFF3F),
+// high nibble first, at one of four volume shifts.
+pub struct Wave {
+    ram: [u8; 0x10],
+    length: Length,
+    enabled: bool,
+    dac_enabled: bool,
+    volume_code: u8,
+    frequency: u16,
+    timer: u16,
+    position: u8,
+}
+
+impl Wave {
+    pub fn new() -> Wave {
+        Wave {
+            ram: [0; 0x10],
+            length: Length::new(256),
+            enabled: false,
+            dac_enabled: false,
+            volume_code: 0,
+            frequency: 0,
+            timer: 0,
+            position: 0,
+        }
+    }
+
+    // Powering the APU off clears every register but leaves wave RAM alone.
+    pub fn reset(&mut self) {
+        let ram = self.ram;
+        *self = Wave::new();
+        self.ram = ram;
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn output(&self) -> Option<u8> {
+        if !self.dac_enabled { return None }
+        if !self.enabled || self.volume_code == 0 { return Some(0) }
+
+        let byte = self.ram[self.position as usize / 2];
+        let sample = if self.position & 0x01 == 0 { byte >> 4 } else { byte & 0x0F };
+        Some(sample >> (self.volume_code - 1))
+    }
+
+    pub fn read_ram(&self, address: u16) -> u8 {
+        self.ram[address as usize - 0xFF30]
+    }
+
+    pub fn write_ram(&mut self, address: u16, value: u8) {
+        self.ram[address as usize - 0xFF30] = value;
+    }
+
+    // Writes NR30-NR34, numbered 0-4.
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.dac_enabled = value & 0x80 != 0;
+                if !self.dac_enabled { self.enabled = false }
+            },
+            1 => self.length.load(value),
+            2 => self.volume_code = (value >> 5) & 0x03,
+            3 => self.frequency = (self.frequency & 0x0700) | value as u16,
+            _ => {
+                self.frequency = (self.frequency & 0x00FF) | ((value as u16 & 0x07) << 8);
+                self.length.enabled = value & 0x40 != 0;
+                if value & 0x80 != 0 { self.trigger() }
+            },
+        }
+    }
+
+    pub fn tick(&mut self) {
+        if self.timer > 0 { self.timer -= 1 }
+        if self.timer == 0 {
+            self.timer = self.period();
+            self.position = (self.position + 1) % 32;
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() { self.enabled = false }
+    }
+
+    // -------------------------------------------------------------------------
+
+    fn period(&self) -> u16 {
+        (2048 - self.frequency) * 2
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.dac_enabled;
+        self.length.trigger();
+        self.timer = self.period();
+        self.position = 0;
+    }
+}
@@ -0,0 +1,57 @@
+// https://gbdev.io/pandocs/Power_Up_Sequence.html
+use self::Model::*;
+
+// The hardware revisions whose boot ROMs leave the machine in a different
+// state, which games occasionally use to tell them apart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Model { Dmg0, Dmg, Mgb, Sgb, Cgb, Agb }
+
+impl Model {
+    pub fn from_name(name: &str) -> Option<Model> {
+        match name.to_ascii_lowercase().as_str() {
+            "dmg0" => Some(Dmg0),
+            "dmg"  => Some(Dmg),
+            "mgb"  => Some(Mgb),
+            "sgb"  => Some(Sgb),
+            "cgb"  => Some(Cgb),
+            "agb"  => Some(Agb),
+            _ => None,
+        }
+    }
+
+    pub fn cgb(self) -> bool {
+        self == Cgb || self == Agb
+    }
+}
+
+// A, F, B, C, D, E, H and L as the boot ROM leaves them. The DMG boot ROM
+// finishes with a comparison that sets H and C unless the header checksum
+// is zero. A CGB running a DMG cartridge also derives B and HL from the
+// title to pick a palette; these are the values for titles it doesn't know.
+pub fn registers(model: Model, cgb_mode: bool, header_checksum: u8) -> [u8; 8] {
+    let checksum_flags = if header_checksum != 0 { 0xB0 } else { 0x80 };
+
+    match model {
+        Dmg0 => [0x01, 0x00, 0xFF, 0x13, 0x00, 0xC1, 0x84, 0x03],
+        Dmg  => [0x01, checksum_flags, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D],
+        Mgb  => [0xFF, checksum_flags, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D],
+        Sgb  => [0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60],
+        Cgb if cgb_mode => [0x11, 0x80, 0x00, 0x00, 0xFF, 0x56, 0x00, 0x0D],
+        Cgb  => [0x11, 0x80, 0x00, 0x00, 0x00, 0x08, 0x00, 0x7C],
+
+        // The AGB boot ROM ends with an extra INC B.
+        Agb if cgb_mode => [0x11, 0x00, 0x01, 0x00, 0xFF, 0x56, 0x00, 0x0D],
+        Agb  => [0x11, 0x00, 0x01, 0x00, 0x00, 0x08, 0x00, 0x7C],
+    }
+}
+
+// The 16-bit counter behind DIV when the boot ROM hands over. Only the DMG
+// boot ROMs take a fixed time, and for DMG0 only DIV itself is known. The
+// SGB and CGB ones vary, so those start from zero.
+pub fn divider(model: Model) -> u16 {
+    match model {
+        Dmg0 => 0x1800,
+        Dmg | Mgb => 0xABCC,
+        _ => 0,
+    }
+}
@@ -0,0 +1,758 @@
+// http://gbdev.gg8.se/wiki/articles/Memory_Map
+use apu::Apu;
+use boot::{self, Model};
+use interrupts::{Interrupt, Interrupts};
+use joypad::{Button, Joypad};
+use mappers::{Mapper, RomOnly};
+use ppu::{Mode, Ppu, Renderer};
+use save::SaveFile;
+use serial::{Serial, SerialLink};
+use timer::Timer;
+use std::io;
+
+pub trait Bus {
+    fn read8(&self, address: u16) -> u8;
+    fn write8(&mut self, address: u16, value: u8);
+
+    fn read16(&self, address: u16) -> u16 {
+        let first_byte = self.read8(address);
+        let second_byte = self.read8(address.wrapping_add(1));
+        ((second_byte as u16) << 8) + first_byte as u16
+    }
+
+    fn write16(&mut self, address: u16, value: u16) {
+        self.write8(address, (value & 0xFF) as u8);
+        self.write8(address.wrapping_add(1), (value >> 8) as u8);
+    }
+}
+
+// CGB VRAM DMA either copies everything at once, or one 16-byte block at
+// the start of each HBlank.
+#[derive(Clone, Copy, PartialEq)]
+enum VramDma { General, HBlank }
+
+pub struct MemoryBus {
+    cartridge: Box<dyn Mapper>,
+    boot_rom: Option<Vec<u8>>,
+    save_file: Option<SaveFile>,
+    cgb: bool,
+    wram: [u8; 0x8000],
+    wram_bank: u8,
+    io: [u8; 0x80],
+    hram: [u8; 0x7F],
+    apu: Apu,
+    interrupts: Interrupts,
+    joypad: Joypad,
+    ppu: Ppu,
+    serial: Serial,
+    timer: Timer,
+    dma: u8,
+    dma_position: Option<u16>,
+    hdma_source: u16,
+    hdma_destination: u16,
+    hdma_length: u8,
+    vram_dma: Option<VramDma>,
+    hblank_started: bool,
+    speed_switch_armed: bool,
+    double_speed: bool,
+}
+
+impl MemoryBus {
+    pub fn new() -> MemoryBus {
+        MemoryBus::with_renderer(Renderer::Scanline)
+    }
+
+    pub fn with_renderer(renderer: Renderer) -> MemoryBus {
+        MemoryBus {
+            cartridge: Box::new(RomOnly::new(Vec::new(), 0)),
+            boot_rom: None,
+            save_file: None,
+            cgb: false,
+            wram: [0; 0x8000],
+            wram_bank: 0,
+            io: [0; 0x80],
+            hram: [0; 0x7F],
+            apu: Apu::new(),
+            interrupts: Interrupts::new(),
+            joypad: Joypad::new(),
+            ppu: Ppu::with_renderer(renderer),
+            serial: Serial::new(),
+            timer: Timer::new(),
+            dma: 0,
+            dma_position: None,
+            hdma_source: 0,
+            hdma_destination: 0,
+            hdma_length: 0x7F,
+            vram_dma: None,
+            hblank_started: false,
+            speed_switch_armed: false,
+            double_speed: false,
+        }
+    }
+
+    pub fn load_cartridge(&mut self, cartridge: Box<dyn Mapper>) {
+        self.cartridge = cartridge;
+    }
+
+    // Maps a boot ROM over the cartridge until a write to 0xFF50. The CGB's
+    // is larger and also covers 0x0200-0x08FF, leaving the header visible.
+    pub fn load_boot_rom(&mut self, boot_rom: Vec<u8>) {
+        self.boot_rom = Some(boot_rom);
+    }
+
+    // Puts the I/O registers in the state the model's boot ROM leaves them.
+    pub fn skip_boot_rom(&mut self, model: Model) {
+        self.timer.set_divider(boot::divider(model));
+        self.joypad.write(0x00, &mut self.interrupts);
+        self.interrupts.write_flags(0x01);
+
+        // Every boot ROM but the SGB's ends with channel 1 still sounding.
+        self.apu.write(0xFF26, 0x80);
+        self.apu.write(0xFF24, 0x77);
+        self.apu.write(0xFF25, 0xF3);
+        self.apu.write(0xFF11, 0x80);
+        self.apu.write(0xFF12, 0xF3);
+        if model != Model::Sgb {
+            self.apu.write(0xFF13, 0xC1);
+            self.apu.write(0xFF14, 0x87);
+        }
+
+        self.ppu.write(0xFF40, 0x91, &mut self.interrupts);
+        self.ppu.write(0xFF47, 0xFC, &mut self.interrupts);
+
+        if model.cgb() {
+            self.serial.write(0xFF02, 0x01);
+        } else {
+            self.dma = 0xFF;
+        }
+    }
+
+    pub fn cgb_mode(&self) -> bool {
+        self.cgb
+    }
+
+    // CGB mode banks VRAM and WRAM and enables the colour palettes and the
+    // speed switch.
+    pub fn set_cgb_mode(&mut self, enabled: bool) {
+        self.cgb = enabled;
+        self.ppu.set_cgb_mode(enabled);
+    }
+
+    pub fn rumble(&self) -> bool {
+        self.cartridge.rumble()
+    }
+
+    // Advances every component clocked alongside the CPU. The PPU runs off
+    // a fixed clock, so it sees half as many dots per cycle in double speed.
+    pub fn tick(&mut self, cycles: u32) {
+        let dots = if self.double_speed { 2 } else { 4 };
+
+        for _ in 0..cycles {
+            self.tick_dma();
+            let div = self.timer.read(0xFF04);
+            self.timer.tick(&mut self.interrupts);
+            self.clock_frame_sequencer(div);
+            self.serial.tick(&mut self.interrupts);
+
+            // An HBlank DMA block is due from the start of an HBlank until
+            // the line moves on, and never while the LCD is off.
+            let mode = self.ppu.mode();
+            self.ppu.tick(dots, &mut self.interrupts);
+            if self.ppu.mode() != Mode::HBlank || self.ppu.read(0xFF40) & 0x80 == 0 {
+                self.hblank_started = false;
+            } else if mode != Mode::HBlank {
+                self.hblank_started = true;
+            }
+
+            self.apu.tick(dots);
+        }
+    }
+
+    pub fn ppu(&self) -> &Ppu {
+        &self.ppu
+    }
+
+    pub fn request_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupts.request(interrupt);
+    }
+
+    pub fn acknowledge_interrupt(&mut self, interrupt: Interrupt) {
+        self.interrupts.acknowledge(interrupt);
+    }
+
+    pub fn pending_interrupt(&self) -> Option<Interrupt> {
+        self.interrupts.pending()
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.apu.set_sample_rate(sample_rate);
+    }
+
+    pub fn take_audio_samples(&mut self) -> Vec<i16> {
+        self.apu.take_samples()
+    }
+
+    pub fn set_channel_capture(&mut self, enabled: bool) {
+        self.apu.set_channel_capture(enabled);
+    }
+
+    pub fn take_channel_samples(&mut self) -> [Vec<i16>; 4] {
+        self.apu.take_channel_samples()
+    }
+
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        self.joypad.set_button(button, pressed, &mut self.interrupts);
+    }
+
+    pub fn set_serial_link(&mut self, link: Box<dyn SerialLink>) {
+        self.serial.set_link(link);
+    }
+
+    pub fn joypad_requested(&self) -> bool {
+        self.interrupts.read_flags() & Interrupt::Joypad.mask() != 0
+    }
+
+    // Whether the CPU should spend its next step on a VRAM DMA block: every
+    // step until a general purpose transfer finishes, or once per HBlank.
+    pub fn vram_dma_due(&self) -> bool {
+        match self.vram_dma {
+            Some(VramDma::General) => true,
+            Some(VramDma::HBlank) => self.hblank_started,
+            None => false,
+        }
+    }
+
+    // Copies one 16-byte block to VRAM, returning the machine cycles the CPU
+    // is held for. The copy runs off the PPU's clock, so twice as many
+    // cycles pass in double speed.
+    pub fn run_vram_dma(&mut self) -> u32 {
+        self.hblank_started = false;
+
+        for _ in 0..0x10 {
+            let value = self.read_unrestricted(self.hdma_source);
+            self.ppu.write_vram(0x8000 | (self.hdma_destination & 0x1FFF), value);
+            self.hdma_source = self.hdma_source.wrapping_add(1);
+            self.hdma_destination = self.hdma_destination.wrapping_add(1);
+        }
+
+        self.hdma_length = self.hdma_length.wrapping_sub(1) & 0x7F;
+        if self.hdma_length == 0x7F { self.vram_dma = None }
+
+        if self.double_speed { 16 } else { 8 }
+    }
+
+    pub fn speed_switch_armed(&self) -> bool {
+        self.speed_switch_armed
+    }
+
+    pub fn double_speed(&self) -> bool {
+        self.double_speed
+    }
+
+    pub fn switch_speed(&mut self) {
+        self.double_speed = !self.double_speed;
+        self.speed_switch_armed = false;
+    }
+
+    pub fn attach_save_file(&mut self, save_file: SaveFile) -> io::Result<()> {
+        if let Some(data) = save_file.read()? {
+            self.cartridge.load_save_data(&data);
+        }
+        self.save_file = Some(save_file);
+        Ok(())
+    }
+
+    pub fn autosave(&mut self) -> io::Result<()> {
+        match self.save_file {
+            Some(ref save_file) if save_file.is_due() => self.flush_save(),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn flush_save(&mut self) -> io::Result<()> {
+        if let Some(ref mut save_file) = self.save_file {
+            if save_file.is_dirty() {
+                save_file.write(&self.cartridge.save_data())?;
+            }
+        }
+        Ok(())
+    }
+
+    // -------------------------------------------------------------------------
+
+    // While an OAM DMA transfer is running the CPU can only reach HRAM and
+    // the I/O registers; everything else, OAM included, reads 0xFF.
+    fn blocked_by_dma(&self, address: u16) -> bool {
+        self.dma_position.is_some() && address < 0xFF00
+    }
+
+    // The APU's frame sequencer counts falling edges of DIV bit 4, or bit 5
+    // in double speed, so resetting DIV can clock it early.
+    fn clock_frame_sequencer(&mut self, old_div: u8) {
+        let bit = if self.double_speed { 0x20 } else { 0x10 };
+        if old_div & bit != 0 && self.timer.read(0xFF04) & bit == 0 {
+            self.apu.step_frame_sequencer();
+        }
+    }
+
+    // The DMG boot ROM covers 0x0000-0x00FF. Past the cartridge header the
+    // CGB's continues from 0x0200.
+    fn read_boot_rom(&self, address: u16) -> Option<u8> {
+        let boot_rom = self.boot_rom.as_ref()?;
+        if (0x0100..0x0200).contains(&address) { return None }
+        boot_rom.get(address as usize).copied()
+    }
+
+    // HDMA5: bit 7 is clear while a transfer is running, and the rest is the
+    // number of blocks left minus one. 0xFF once a transfer has finished.
+    fn read_hdma5(&self) -> u8 {
+        if self.vram_dma.is_some() { self.hdma_length } else { 0x80 | self.hdma_length }
+    }
+
+    // KEY1: bit 7 is the current speed, bit 0 a pending switch.
+    fn read_key1(&self) -> u8 {
+        if !self.cgb { return 0xFF }
+
+        let mut value = 0x7E;
+        if self.double_speed       { value |= 0x80 }
+        if self.speed_switch_armed { value |= 0x01 }
+        value
+    }
+
+    fn read_unrestricted(&self, address: u16) -> u8 {
+        let offset = address as usize;
+
+        match offset {
+            0x0000..=0x7FFF => {
+                self.read_boot_rom(address).unwrap_or_else(|| self.cartridge.read_rom(address))
+            },
+            0x8000..=0x9FFF => self.ppu.read_vram(address),
+            0xA000..=0xBFFF => self.cartridge.read_ram(address),
+            0xC000..=0xFDFF => self.wram[self.wram_offset(address)],
+            0xFE00..=0xFE9F => self.ppu.read_oam(address),
+            0xFEA0..=0xFEFF => 0xFF,
+            0xFF00 => self.joypad.read(),
+            0xFF01 | 0xFF02 => self.serial.read(address),
+            0xFF04..=0xFF07 => self.timer.read(address),
+            0xFF0F => self.interrupts.read_flags(),
+            0xFF10..=0xFF3F => self.apu.read(address),
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B | 0xFF4F | 0xFF68..=0xFF6B => {
+                self.ppu.read(address)
+            },
+            0xFF46 => self.dma,
+            0xFF4D => self.read_key1(),
+            0xFF51..=0xFF54 if self.cgb => 0xFF,
+            0xFF55 if self.cgb => self.read_hdma5(),
+            0xFF70 if self.cgb => 0xF8 | self.wram_bank,
+            0xFF03..=0xFF7F => self.io[offset - 0xFF00],
+            0xFF80..=0xFFFE => self.hram[offset - 0xFF80],
+            _ => self.interrupts.read_enable(),
+        }
+    }
+
+    // OAM DMA copies one byte per machine cycle from XX00-XX9F, where XX is
+    // the value written to 0xFF46. Sources above 0xDF hit the WRAM echo.
+    // http://gbdev.gg8.se/wiki/articles/Video_Display#LCD_OAM_DMA_Transfers
+    fn tick_dma(&mut self) {
+        let position = match self.dma_position {
+            Some(position) => position,
+            None => return,
+        };
+
+        let mut source = ((self.dma as u16) << 8) + position;
+        if source >= 0xE000 { source -= 0x2000 }
+
+        let value = self.read_unrestricted(source);
+        self.ppu.write_oam(0xFE00 + position, value);
+
+        self.dma_position = if position + 1 < 0xA0 { Some(position + 1) } else { None };
+    }
+
+    // Starts a transfer of (value & 0x7F) + 1 blocks, in HBlank mode if bit 7
+    // is set. Writing with bit 7 clear during an HBlank transfer stops it.
+    // http://gbdev.gg8.se/wiki/articles/Video_Display#LCD_VRAM_DMA_Transfers_.28CGB_only.29
+    fn write_hdma5(&mut self, value: u8) {
+        if self.vram_dma == Some(VramDma::HBlank) && value & 0x80 == 0 {
+            self.vram_dma = None;
+            return;
+        }
+
+        self.hdma_length = value & 0x7F;
+        self.hblank_started = false;
+        self.vram_dma = Some(if value & 0x80 != 0 { VramDma::HBlank } else { VramDma::General });
+    }
+
+    // KEY0: the CGB boot ROM sets bit 2 to run a DMG cartridge in
+    // compatibility mode, which drops the CGB-only features. It is locked
+    // once the boot ROM is unmapped.
+    fn write_key0(&mut self, value: u8) {
+        if self.cgb && self.boot_rom.is_some() && value & 0x04 != 0 {
+            self.set_cgb_mode(false);
+        }
+    }
+
+    // WRAM is 4KiB fixed at 0xC000 and a 4KiB bank at 0xD000, selected by
+    // SVBK in CGB mode, both echoed from 0xE000.
+    fn wram_offset(&self, address: u16) -> usize {
+        let offset = (address as usize - 0xC000) & 0x1FFF;
+        if offset < 0x1000 { return offset }
+
+        let bank = if self.cgb { self.wram_bank.max(1) as usize } else { 1 };
+        bank * 0x1000 + offset - 0x1000
+    }
+}
+
+impl Default for MemoryBus {
+    fn default() -> MemoryBus {
+        MemoryBus::new()
+    }
+}
+
+impl Bus for MemoryBus {
+    fn read8(&self, address: u16) -> u8 {
+        if self.blocked_by_dma(address) { return 0xFF }
+        self.read_unrestricted(address)
+    }
+
+    fn write8(&mut self, address: u16, value: u8) {
+        if self.blocked_by_dma(address) { return }
+        let offset = address as usize;
+
+        match offset {
+            0x0000..=0x7FFF => self.cartridge.write_rom(address, value),
+            0x8000..=0x9FFF => self.ppu.write_vram(address, value),
+            0xA000..=0xBFFF => {
+                if self.cartridge.write_ram(address, value) {
+                    if let Some(ref mut save_file) = self.save_file {
+                        save_file.mark_dirty();
+                    }
+                }
+            },
+            0xC000..=0xFDFF => self.wram[self.wram_offset(address)] = value,
+            0xFE00..=0xFE9F => self.ppu.write_oam(address, value),
+            0xFEA0..=0xFEFF => (),
+            0xFF00 => self.joypad.write(value, &mut self.interrupts),
+            0xFF01 | 0xFF02 => self.serial.write(address, value),
+            0xFF04..=0xFF07 => {
+                let div = self.timer.read(0xFF04);
+                self.timer.write(address, value);
+                self.clock_frame_sequencer(div);
+            },
+            0xFF0F => self.interrupts.write_flags(value),
+            0xFF10..=0xFF3F => self.apu.write(address, value),
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B | 0xFF4F | 0xFF68..=0xFF6B => {
+                self.ppu.write(address, value, &mut self.interrupts);
+            },
+            0xFF46 => {
+                self.dma = value;
+                self.dma_position = Some(0);
+            },
+            0xFF4C => self.write_key0(value),
+            0xFF4D => if self.cgb { self.speed_switch_armed = value & 0x01 == 0x01 },
+            0xFF50 => if value != 0 { self.boot_rom = None },
+            0xFF51 if self.cgb => {
+                self.hdma_source = (self.hdma_source & 0x00FF) | (value as u16) << 8;
+            },
+            0xFF52 if self.cgb => {
+                self.hdma_source = (self.hdma_source & 0xFF00) | (value & 0xF0) as u16;
+            },
+            0xFF53 if self.cgb => {
+                self.hdma_destination = (self.hdma_destination & 0x00FF) | ((value & 0x1F) as u16) << 8;
+            },
+            0xFF54 if self.cgb => {
+                self.hdma_destination = (self.hdma_destination & 0xFF00) | (value & 0xF0) as u16;
+            },
+            0xFF55 if self.cgb => self.write_hdma5(value),
+            0xFF70 if self.cgb => self.wram_bank = value & 0x07,
+            0xFF03..=0xFF7F => self.io[offset - 0xFF00] = value,
+            0xFF80..=0xFFFE => self.hram[offset - 0xFF80] = value,
+            _ => self.interrupts.write_enable(value),
+        }
+    }
+}
+
+impl Drop for MemoryBus {
+    fn drop(&mut self) {
+        if let Err(error) = self.flush_save() {
+            eprintln!("Unable to write save file: {}", error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mappers::{Mbc3, RomOnly};
+    use mappers::rtc::{Clock, Rtc};
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    struct FixedClock;
+
+    impl Clock for FixedClock {
+        fn now(&self) -> u64 { 1_500_000_000 }
+    }
+
+    // An MBC3 cartridge with 8KiB of RAM and a clock, with RAM enabled.
+    fn mbc3_bus() -> MemoryBus {
+        let rtc = Rtc::new(Box::new(FixedClock));
+        let mut bus = MemoryBus::new();
+        bus.load_cartridge(Box::new(Mbc3::new(vec![0; 0x8000], 0x2000, Some(rtc))));
+        bus.write8(0x0000, 0x0A);
+        bus
+    }
+
+    fn rom_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("rusty-boy-{}-{}.gb", name, ::std::process::id()))
+    }
+
+    #[test]
+    fn echo_ram_mirrors_wram() {
+        let mut bus = MemoryBus::new();
+        bus.write8(0xC123, 0x42);
+        assert_eq!(bus.read8(0xE123), 0x42);
+
+        bus.write8(0xFDFF, 0x24);
+        assert_eq!(bus.read8(0xDDFF), 0x24);
+    }
+
+    #[test]
+    fn unusable_region_reads_ff_and_ignores_writes() {
+        let mut bus = MemoryBus::new();
+        bus.write8(0xFEA0, 0x00);
+        assert_eq!(bus.read8(0xFEA0), 0xFF);
+        assert_eq!(bus.read8(0xFEFF), 0xFF);
+    }
+
+    #[test]
+    fn hram_and_ie_are_separate() {
+        let mut bus = MemoryBus::new();
+        bus.write8(0xFFFE, 0x12);
+        bus.write8(0xFFFF, 0x1F);
+        assert_eq!(bus.read8(0xFFFE), 0x12);
+        assert_eq!(bus.read8(0xFFFF), 0x1F);
+    }
+
+    #[test]
+    fn sixteen_bit_accesses_are_little_endian() {
+        let mut bus = MemoryBus::new();
+        bus.write16(0xFFFC, 0xBEEF);
+        assert_eq!(bus.read8(0xFFFC), 0xEF);
+        assert_eq!(bus.read8(0xFFFD), 0xBE);
+        assert_eq!(bus.read16(0xFFFC), 0xBEEF);
+    }
+
+    #[test]
+    fn rom_writes_go_to_the_mapper_not_rom() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0150] = 0x3E;
+
+        let mut bus = MemoryBus::new();
+        bus.load_cartridge(Box::new(RomOnly::new(rom, 0)));
+        bus.write8(0x0150, 0x00);
+        assert_eq!(bus.read8(0x0150), 0x3E);
+    }
+
+    #[test]
+    fn save_round_trips_ram_and_clock() {
+        let rom_path = rom_path("round-trip");
+        let save_path = rom_path.with_extension("sav");
+
+        let mut bus = mbc3_bus();
+        bus.attach_save_file(SaveFile::for_rom(&rom_path)).unwrap();
+        bus.write8(0xA000, 0x42);
+        bus.write8(0xBFFF, 0x24);
+        bus.write8(0x4000, 0x08);
+        bus.write8(0xA000, 0x15);
+        bus.flush_save().unwrap();
+
+        let data = fs::read(&save_path).unwrap();
+        assert_eq!(data.len(), 0x2000 + 48);
+        assert_eq!(data[0x0000], 0x42);
+        assert_eq!(data[0x1FFF], 0x24);
+        assert_eq!(&data[0x2000..0x2004], &[0x15, 0, 0, 0]);
+        assert_eq!(&data[0x2028..], &1_500_000_000u64.to_le_bytes());
+
+        let mut reloaded = mbc3_bus();
+        reloaded.attach_save_file(SaveFile::for_rom(&rom_path)).unwrap();
+        assert_eq!(reloaded.read8(0xA000), 0x42);
+        assert_eq!(reloaded.read8(0xBFFF), 0x24);
+
+        reloaded.write8(0x4000, 0x08);
+        reloaded.write8(0x6000, 0x00);
+        reloaded.write8(0x6000, 0x01);
+        assert_eq!(reloaded.read8(0xA000), 0x15);
+
+        fs::remove_file(&save_path).unwrap();
+    }
+
+    #[test]
+    fn ignored_ram_writes_leave_save_clean() {
+        let rom_path = rom_path("ignored-writes");
+
+        let mut bus = mbc3_bus();
+        bus.attach_save_file(SaveFile::for_rom(&rom_path)).unwrap();
+        bus.write8(0x0000, 0x00);
+        bus.write8(0xA000, 0x42);
+
+        bus.write8(0x0000, 0x0A);
+        bus.write8(0x4000, 0x08);
+        bus.write8(0xA000, 0x15);
+
+        assert!(!bus.save_file.as_ref().unwrap().is_dirty());
+    }
+
+    #[test]
+    fn oam_dma_blocks_everything_below_io_for_160_cycles() {
+        let mut bus = MemoryBus::new();
+        for offset in 0..0xA0 {
+            bus.write8(0xC000 + offset, offset as u8 ^ 0x5A);
+        }
+
+        bus.write8(0xFF46, 0xC0);
+        bus.write8(0xFF80, 0x12);
+        assert_eq!(bus.read8(0xC000), 0xFF);
+        assert_eq!(bus.read8(0xFE00), 0xFF);
+        assert_eq!(bus.read8(0xFF80), 0x12);
+        assert_eq!(bus.read8(0xFF46), 0xC0);
+
+        bus.tick(159);
+        assert_eq!(bus.read8(0xC000), 0xFF);
+
+        bus.tick(1);
+        assert_eq!(bus.read8(0xC000), 0x5A);
+        for offset in 0..0xA0 {
+            assert_eq!(bus.read8(0xFE00 + offset), offset as u8 ^ 0x5A);
+        }
+    }
+
+    #[test]
+    fn svbk_switches_upper_wram_bank_in_cgb_mode() {
+        let mut bus = MemoryBus::new();
+        bus.set_cgb_mode(true);
+
+        bus.write8(0xC000, 0x99);
+        bus.write8(0xFF70, 0x00);
+        bus.write8(0xD000, 0x11);
+        bus.write8(0xFF70, 0x02);
+        assert_eq!(bus.read8(0xFF70), 0xFA);
+        assert_eq!(bus.read8(0xD000), 0x00);
+        bus.write8(0xD000, 0x22);
+        assert_eq!(bus.read8(0xF000), 0x22);
+        assert_eq!(bus.read8(0xC000), 0x99);
+
+        bus.write8(0xFF70, 0x01);
+        assert_eq!(bus.read8(0xD000), 0x11);
+    }
+
+    // A CGB bus with the LCD on and a VRAM DMA from 0xC000 to 0x8000
+    // started by writing the given value to HDMA5.
+    fn vram_dma_bus(hdma5: u8) -> MemoryBus {
+        let mut bus = MemoryBus::new();
+        bus.set_cgb_mode(true);
+        for offset in 0..0x100 {
+            bus.write8(0xC000 + offset, offset as u8);
+        }
+        bus.write8(0xFF40, 0x80);
+        bus.write8(0xFF51, 0xC0);
+        bus.write8(0xFF52, 0x00);
+        bus.write8(0xFF53, 0x00);
+        bus.write8(0xFF54, 0x00);
+        bus.write8(0xFF55, hdma5);
+        bus
+    }
+
+    fn tick_until_dma_due(bus: &mut MemoryBus) {
+        while !bus.vram_dma_due() {
+            bus.tick(1);
+        }
+    }
+
+    #[test]
+    fn hblank_dma_moves_one_block_per_hblank() {
+        let mut bus = vram_dma_bus(0x82);
+        assert!(!bus.vram_dma_due());
+
+        tick_until_dma_due(&mut bus);
+        assert_eq!(bus.ppu().mode(), Mode::HBlank);
+        assert_eq!(bus.run_vram_dma(), 8);
+        assert_eq!(bus.read8(0xFF55), 0x01);
+        assert_eq!(bus.ppu().read_vram(0x800F), 0x0F);
+        assert_eq!(bus.ppu().read_vram(0x8010), 0x00);
+
+        while bus.ppu().mode() == Mode::HBlank {
+            assert!(!bus.vram_dma_due());
+            bus.tick(1);
+        }
+
+        tick_until_dma_due(&mut bus);
+        bus.run_vram_dma();
+        assert_eq!(bus.read8(0xFF55), 0x00);
+        assert_eq!(bus.ppu().read_vram(0x801F), 0x1F);
+    }
+
+    #[test]
+    fn hblank_dma_block_is_dropped_when_the_line_ends() {
+        let mut bus = vram_dma_bus(0x81);
+
+        tick_until_dma_due(&mut bus);
+        while bus.ppu().mode() == Mode::HBlank {
+            bus.tick(1);
+        }
+        assert!(!bus.vram_dma_due());
+
+        tick_until_dma_due(&mut bus);
+        bus.write8(0xFF40, 0x00);
+        bus.tick(1);
+        assert!(!bus.vram_dma_due());
+        assert_eq!(bus.read8(0xFF55), 0x01);
+    }
+
+    #[test]
+    fn cancelled_hblank_dma_reports_remaining_blocks() {
+        let mut bus = vram_dma_bus(0x83);
+
+        tick_until_dma_due(&mut bus);
+        bus.run_vram_dma();
+        bus.write8(0xFF55, 0x00);
+
+        assert!(!bus.vram_dma_due());
+        assert_eq!(bus.read8(0xFF55), 0x82);
+        bus.tick(456);
+        assert!(!bus.vram_dma_due());
+    }
+
+    #[test]
+    fn general_dma_runs_every_step_until_done() {
+        let mut bus = vram_dma_bus(0x01);
+
+        assert!(bus.vram_dma_due());
+        assert_eq!(bus.run_vram_dma(), 8);
+        assert!(bus.vram_dma_due());
+        bus.switch_speed();
+        assert_eq!(bus.run_vram_dma(), 16);
+        assert!(!bus.vram_dma_due());
+        assert_eq!(bus.read8(0xFF55), 0xFF);
+    }
+
+    #[test]
+    fn boot_rom_covers_cartridge_until_unmapped() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0000] = 0xC3;
+        rom[0x0100] = 0x00;
+        rom[0x0200] = 0x2A;
+
+        let mut bus = MemoryBus::new();
+        bus.load_cartridge(Box::new(RomOnly::new(rom, 0)));
+        bus.load_boot_rom(vec![0x31; 0x900]);
+
+        assert_eq!(bus.read8(0x0000), 0x31);
+        assert_eq!(bus.read8(0x0100), 0x00);
+        assert_eq!(bus.read8(0x0200), 0x31);
+
+        bus.write8(0xFF50, 0x01);
+        assert_eq!(bus.read8(0x0000), 0xC3);
+        assert_eq!(bus.read8(0x0200), 0x2A);
+    }
+}
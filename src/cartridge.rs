@@ -0,0 +1,260 @@
+// http://gbdev.gg8.se/wiki/articles/The_Cartridge_Header
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+const HEADER_END: usize = 0x0150;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CgbSupport { None, Enhanced, Only }
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MapperType { RomOnly, Mbc1, Mbc2, Mbc3, Mbc5 }
+
+#[derive(Clone, Copy, Debug)]
+pub struct CartridgeType {
+    pub code: u8,
+    pub mapper: MapperType,
+    pub ram: bool,
+    pub battery: bool,
+    pub timer: bool,
+    pub rumble: bool,
+}
+
+#[derive(Clone, Debug)]
+pub enum Licensee {
+    Old(u8),
+    New(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct CartridgeHeader {
+    pub title: String,
+    pub manufacturer_code: Option<String>,
+    pub cgb_support: CgbSupport,
+    pub sgb_support: bool,
+    pub cartridge_type: CartridgeType,
+    pub rom_size: usize,
+    pub ram_size: usize,
+    pub licensee: Licensee,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    TooSmall(usize),
+    UnsupportedCartridgeType(u8),
+    InvalidRomSize(u8),
+    InvalidRamSize(u8),
+    RomSizeMismatch { expected: usize, actual: usize },
+    HeaderChecksum { expected: u8, actual: u8 },
+    GlobalChecksum { expected: u16, actual: u16 },
+}
+
+impl CartridgeHeader {
+    pub fn parse(rom: &[u8]) -> Result<CartridgeHeader, CartridgeError> {
+        if rom.len() < HEADER_END {
+            return Err(CartridgeError::TooSmall(rom.len()));
+        }
+
+        let header_checksum = rom[0x014D];
+        let actual = header_checksum_for(rom);
+        if actual != header_checksum {
+            return Err(CartridgeError::HeaderChecksum {
+                expected: header_checksum,
+                actual,
+            });
+        }
+
+        let cartridge_type = CartridgeType::from_byte(rom[0x0147])?;
+        let rom_size = rom_size_for(rom[0x0148])?;
+        let ram_size = ram_size_for(rom[0x0149])?;
+
+        if rom.len() != rom_size {
+            return Err(CartridgeError::RomSizeMismatch {
+                expected: rom_size,
+                actual: rom.len(),
+            });
+        }
+
+        let global_checksum = ((rom[0x014E] as u16) << 8) + rom[0x014F] as u16;
+        let actual = global_checksum_for(rom);
+        if actual != global_checksum {
+            return Err(CartridgeError::GlobalChecksum {
+                expected: global_checksum,
+                actual,
+            });
+        }
+
+        let cgb_support = match rom[0x0143] {
+            0xC0 => CgbSupport::Only,
+            0x80 => CgbSupport::Enhanced,
+            _ => CgbSupport::None,
+        };
+
+        // Later cartridges shortened the title to make room for a
+        // manufacturer code and the CGB flag.
+        let (title, manufacturer_code) = if cgb_support == CgbSupport::None {
+            (ascii_for(&rom[0x0134..0x0144]), None)
+        } else {
+            let code = &rom[0x013F..0x0143];
+            if code.iter().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit()) {
+                (ascii_for(&rom[0x0134..0x013F]), Some(ascii_for(code)))
+            } else {
+                (ascii_for(&rom[0x0134..0x0143]), None)
+            }
+        };
+
+        let licensee = match rom[0x014B] {
+            0x33 => Licensee::New(ascii_for(&rom[0x0144..0x0146])),
+            code => Licensee::Old(code),
+        };
+
+        Ok(CartridgeHeader {
+            title,
+            manufacturer_code,
+            cgb_support,
+            sgb_support: rom[0x0146] == 0x03,
+            cartridge_type,
+            rom_size,
+            ram_size,
+            licensee,
+            version: rom[0x014C],
+            header_checksum,
+            global_checksum,
+        })
+    }
+}
+
+impl CartridgeType {
+    pub fn from_byte(byte: u8) -> Result<CartridgeType, CartridgeError> {
+        use self::MapperType::*;
+
+        let (mapper, ram, battery, timer, rumble) = match byte {
+            0x00 => (RomOnly, false, false, false, false),
+            0x01 => (Mbc1,    false, false, false, false),
+            0x02 => (Mbc1,    true,  false, false, false),
+            0x03 => (Mbc1,    true,  true,  false, false),
+            0x05 => (Mbc2,    false, false, false, false),
+            0x06 => (Mbc2,    false, true,  false, false),
+            0x08 => (RomOnly, true,  false, false, false),
+            0x09 => (RomOnly, true,  true,  false, false),
+            0x0F => (Mbc3,    false, true,  true,  false),
+            0x10 => (Mbc3,    true,  true,  true,  false),
+            0x11 => (Mbc3,    false, false, false, false),
+            0x12 => (Mbc3,    true,  false, false, false),
+            0x13 => (Mbc3,    true,  true,  false, false),
+            0x19 => (Mbc5,    false, false, false, false),
+            0x1A => (Mbc5,    true,  false, false, false),
+            0x1B => (Mbc5,    true,  true,  false, false),
+            0x1C => (Mbc5,    false, false, false, true),
+            0x1D => (Mbc5,    true,  false, false, true),
+            0x1E => (Mbc5,    true,  true,  false, true),
+            _ => return Err(CartridgeError::UnsupportedCartridgeType(byte)),
+        };
+
+        Ok(CartridgeType { code: byte, mapper, ram, battery, timer, rumble })
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+fn ascii_for(bytes: &[u8]) -> String {
+    bytes.iter()
+        .take_while(|&&b| b != 0)
+        .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '?' })
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+fn global_checksum_for(rom: &[u8]) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|&(i, _)| i != 0x014E && i != 0x014F)
+        .fold(0u16, |sum, (_, &b)| sum.wrapping_add(b as u16))
+}
+
+fn header_checksum_for(rom: &[u8]) -> u8 {
+    rom[0x0134..0x014D]
+        .iter()
+        .fold(0u8, |sum, &b| sum.wrapping_sub(b).wrapping_sub(1))
+}
+
+fn ram_size_for(byte: u8) -> Result<usize, CartridgeError> {
+    match byte {
+        0x00 => Ok(0),
+        0x01 => Ok(0x800),
+        0x02 => Ok(0x2000),
+        0x03 => Ok(0x8000),
+        0x04 => Ok(0x20000),
+        0x05 => Ok(0x10000),
+        _ => Err(CartridgeError::InvalidRamSize(byte)),
+    }
+}
+
+fn rom_size_for(byte: u8) -> Result<usize, CartridgeError> {
+    match byte {
+        0x00..=0x08 => Ok(0x8000 << byte),
+        _ => Err(CartridgeError::InvalidRomSize(byte)),
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+impl Display for CartridgeHeader {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "Title:\t\t{}", self.title)?;
+        if let Some(ref code) = self.manufacturer_code {
+            writeln!(f, "Manufacturer:\t{}", code)?;
+        }
+        match self.licensee {
+            Licensee::Old(code) => writeln!(f, "Licensee:\t{:#04X}", code)?,
+            Licensee::New(ref code) => writeln!(f, "Licensee:\t{}", code)?,
+        }
+        writeln!(f, "CGB:\t\t{:?}", self.cgb_support)?;
+        writeln!(f, "SGB:\t\t{}", self.sgb_support)?;
+        writeln!(f, "Type:\t\t{}", self.cartridge_type)?;
+        writeln!(f, "ROM:\t\t{} KiB", self.rom_size / 1024)?;
+        writeln!(f, "RAM:\t\t{} KiB", self.ram_size / 1024)?;
+        writeln!(f, "Version:\t{}", self.version)?;
+        write!(f, "Checksums:\t{:#04X} {:#06X}", self.header_checksum, self.global_checksum)
+    }
+}
+
+impl Display for CartridgeType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{:#04X} {:?}", self.code, self.mapper)?;
+        if self.ram     { write!(f, "+RAM")? }
+        if self.battery { write!(f, "+BATTERY")? }
+        if self.timer   { write!(f, "+TIMER")? }
+        if self.rumble  { write!(f, "+RUMBLE")? }
+        Ok(())
+    }
+}
+
+impl Display for CartridgeError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        use self::CartridgeError::*;
+
+        match *self {
+            TooSmall(size) =>
+                write!(f, "ROM is {} bytes, too small to contain a header", size),
+            UnsupportedCartridgeType(code) =>
+                write!(f, "unsupported cartridge type {:#04X}", code),
+            InvalidRomSize(code) =>
+                write!(f, "invalid ROM size code {:#04X}", code),
+            InvalidRamSize(code) =>
+                write!(f, "invalid RAM size code {:#04X}", code),
+            RomSizeMismatch { expected, actual } =>
+                write!(f, "header declares {} bytes of ROM but file has {}", expected, actual),
+            HeaderChecksum { expected, actual } =>
+                write!(f, "header checksum is {:#04X} but header sums to {:#04X}", expected, actual),
+            GlobalChecksum { expected, actual } =>
+                write!(f, "global checksum is {:#06X} but ROM sums to {:#06X}", expected, actual),
+        }
+    }
+}
+
+impl Error for CartridgeError {}
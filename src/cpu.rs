@@ -1,51 +1,274 @@
-use disassembler::log_instruction;
+use boot::{self, Model};
+use bus::{Bus, MemoryBus};
+use disassembler::{log_cb_instruction, log_instruction};
 use flags::Flags;
-use instructions::Instruction;
+use instructions::{CbInstruction, Instruction};
+use joypad::Button;
+use mappers::Mapper;
+use ppu::{Ppu, Renderer};
 use registers::{*, RegisterPair::*};
+use save::SaveFile;
+use serial::SerialLink;
+use std::io;
+
+#[derive(Debug)]
+pub enum StopReason {
+    Halted,
+
+    // A jump to its own address with interrupts disabled is how test ROMs
+    // signal that they have finished.
+    InfiniteLoop(u16),
+
+    Stopped,
+}
 
 pub struct Cpu {
+    bus: MemoryBus,
+    cycles: u64,
+    ei_delay: u8,
     flags: Flags,
-    memory: Vec<u8>,
+    halt_bug: bool,
+    halted: bool,
+    interrupt_master_enable: bool,
+    logging: bool,
     program_counter: u16,
     registers: Registers,
     stack_pointer: u16,
+    stopped: bool,
 }
 
 impl Cpu {
     pub fn new() -> Cpu {
+        Cpu::with_renderer(Renderer::Scanline)
+    }
+
+    pub fn with_renderer(renderer: Renderer) -> Cpu {
         Cpu {
+            bus: MemoryBus::with_renderer(renderer),
+            cycles: 0,
+            ei_delay: 0,
             flags: Flags::new(),
-            memory: Vec::<u8>::new(),
+            halt_bug: false,
+            halted: false,
+            interrupt_master_enable: false,
+            logging: true,
             program_counter: 0,
             registers: Registers::new(),
             stack_pointer: 0,
+            stopped: false,
         }
     }
 
-    pub fn load_memory(&mut self, memory: Vec<u8>) {
-        self.memory = memory;
+    pub fn load_cartridge(&mut self, cartridge: Box<dyn Mapper>) {
+        self.bus.load_cartridge(cartridge);
+    }
+
+    // Runs a boot ROM from 0x0000 in the power-on state, with the CGB
+    // features available from the start for CGB models.
+    pub fn load_boot_rom(&mut self, model: Model, boot_rom: Vec<u8>) {
+        self.bus.set_cgb_mode(model.cgb());
+        self.bus.load_boot_rom(boot_rom);
+    }
+
+    // Starts at 0x0100 in the state the model's boot ROM would have left
+    // behind, so must follow load_cartridge. CGB models only stay in CGB
+    // mode for cartridges that support it.
+    pub fn skip_boot_rom(&mut self, model: Model) {
+        let cgb_mode = model.cgb() && self.bus.read8(0x0143) & 0x80 != 0;
+        self.bus.set_cgb_mode(cgb_mode);
+        self.bus.skip_boot_rom(model);
+
+        let [a, f, b, c, d, e, h, l] = boot::registers(model, cgb_mode, self.bus.read8(0x014D));
+        self.registers.a = a;
+        self.flags = Flags::from_byte(f);
+        self.registers.set_pair(BC, (b as u16) << 8 | c as u16);
+        self.registers.set_pair(DE, (d as u16) << 8 | e as u16);
+        self.registers.set_pair(HL, (h as u16) << 8 | l as u16);
+        self.stack_pointer = 0xFFFE;
+        self.program_counter = 0x0100;
+    }
+
+    pub fn rumble(&self) -> bool {
+        self.bus.rumble()
     }
 
-    pub fn run(&mut self) {
-        while (self.program_counter as usize) < self.memory.len() {
+    pub fn attach_save_file(&mut self, save_file: SaveFile) -> io::Result<()> {
+        self.bus.attach_save_file(save_file)
+    }
+
+    pub fn flush_save(&mut self) -> io::Result<()> {
+        self.bus.flush_save()
+    }
+
+    // Total machine cycles executed since power on.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn run(&mut self) -> StopReason {
+        let reason = loop {
+            if self.halted && self.bus.read8(0xFFFF) & 0x1F == 0 {
+                break StopReason::Halted;
+            }
+
+            // Only a button press ends STOP, and nothing here can press one.
+            if self.stopped {
+                break StopReason::Stopped;
+            }
+
             let starting_program_counter = self.program_counter;
+            let halt_bug = self.halt_bug;
+            let vram_dma = self.vram_dma_due();
+
+            self.step();
+
+            if self.program_counter == starting_program_counter
+                && !halt_bug
+                && !vram_dma
+                && !self.interrupts_possible() {
+                break StopReason::InfiniteLoop(starting_program_counter);
+            }
+
+            if let Err(error) = self.bus.autosave() {
+                eprintln!("Unable to write save file: {}", error);
+            }
+        };
+
+        println!("{:?}", self.registers);
+        println!("{:?}", self.flags);
+        println!("Cycles {{ m: {} }}", self.cycles);
+        reason
+    }
+
+    pub fn ppu(&self) -> &Ppu {
+        self.bus.ppu()
+    }
+
+    pub fn double_speed(&self) -> bool {
+        self.bus.double_speed()
+    }
+
+    // Audio is only generated once a sample rate has been set; samples are
+    // interleaved left then right.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.bus.set_sample_rate(sample_rate);
+    }
+
+    pub fn take_audio_samples(&mut self) -> Vec<i16> {
+        self.bus.take_audio_samples()
+    }
+
+    // One mono buffer per APU channel, at the same sample rate.
+    pub fn set_channel_capture(&mut self, enabled: bool) {
+        self.bus.set_channel_capture(enabled);
+    }
+
+    pub fn take_channel_samples(&mut self) -> [Vec<i16>; 4] {
+        self.bus.take_channel_samples()
+    }
+
+    // Whether every executed instruction is disassembled to stdout.
+    pub fn set_logging(&mut self, enabled: bool) {
+        self.logging = enabled;
+    }
+
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        self.bus.set_button(button, pressed);
+    }
+
+    pub fn set_serial_link(&mut self, link: Box<dyn SerialLink>) {
+        self.bus.set_serial_link(link);
+    }
+
+    // Executes a single instruction, or dispatches an interrupt, and returns
+    // the machine cycles it took so that the rest of the system can be
+    // advanced by the same amount. A halted or stopped CPU idles for one
+    // cycle at a time.
+    pub fn step(&mut self) -> u32 {
+        // HALT ends once any enabled interrupt is requested, whether or not
+        // IME allows it to be serviced.
+        if self.halted && self.bus.pending_interrupt().is_some() {
+            self.halted = false;
+        }
+
+        if self.stopped && self.bus.joypad_requested() {
+            self.stopped = false;
+        }
 
+        // VRAM DMA holds the CPU while each block is copied.
+        let cycles = if self.vram_dma_due() {
+            self.bus.run_vram_dma()
+        } else if let Some(cycles) = self.dispatch_interrupt() {
+            cycles
+        } else if self.halted || self.stopped {
+            1
+        } else {
             let byte = self.get_next_byte();
             let instruction = Instruction::from_byte(byte);
 
-            log_instruction(instruction, &self.memory, self.program_counter);
-            self.process_instruction(instruction);
+            // The HALT bug: the byte after HALT is read twice because PC
+            // fails to advance past it.
+            if self.halt_bug {
+                self.program_counter = self.program_counter.wrapping_sub(1);
+                self.halt_bug = false;
+            }
 
-            if self.program_counter < starting_program_counter { break }
+            if self.logging {
+                log_instruction(instruction, &self.bus, self.program_counter);
+            }
+            self.process_instruction(instruction)
+        };
+
+        // EI takes effect only after the instruction that follows it.
+        if self.ei_delay > 0 {
+            self.ei_delay -= 1;
+            if self.ei_delay == 0 { self.interrupt_master_enable = true }
         }
 
-        println!("{:?}", self.registers);
-        println!("{:?}", self.flags);
+        self.bus.tick(cycles);
+        self.cycles += cycles as u64;
+        cycles
     }
 
     // -------------------------------------------------------------------------
 
-    fn process_instruction(&mut self, instruction: Instruction) {
+    // VRAM DMA pauses along with the CPU in HALT and STOP.
+    fn vram_dma_due(&self) -> bool {
+        !self.halted && !self.stopped && self.bus.vram_dma_due()
+    }
+
+    // A jump to the same address, or a HALT, is only an infinite loop if no
+    // interrupt can ever break out of it. A handler needs IME set or about
+    // to be, but HALT wakes on any enabled interrupt even with IME clear.
+    fn interrupts_possible(&self) -> bool {
+        let enabled = self.bus.read8(0xFFFF) & 0x1F != 0;
+        enabled && (self.halted || self.interrupt_master_enable || self.ei_delay > 0)
+    }
+
+    // Dispatch pushes PC and jumps to the interrupt's vector, taking five
+    // machine cycles.
+    fn dispatch_interrupt(&mut self) -> Option<u32> {
+        if !self.interrupt_master_enable { return None }
+
+        let interrupt = self.bus.pending_interrupt()?;
+        self.interrupt_master_enable = false;
+        self.bus.acknowledge_interrupt(interrupt);
+
+        // If the HALT bug is pending, the interrupt returns to the HALT
+        // itself rather than rereading the byte after it.
+        let mut return_address = self.program_counter;
+        if self.halt_bug {
+            return_address = return_address.wrapping_sub(1);
+            self.halt_bug = false;
+        }
+        self.push(return_address);
+        self.program_counter = interrupt.vector();
+        Some(5)
+    }
+
+    // -------------------------------------------------------------------------
+
+    fn process_instruction(&mut self, instruction: Instruction) -> u32 {
         use instructions::Instruction::*;
 
         match instruction {
@@ -85,35 +308,195 @@ impl Cpu {
                 self.registers.a = self.add(lhs, rhs, false);
             },
 
+            ADD_HL_rp(rp) => {
+                let rhs = self.registers.pair(rp);
+                self.add_hl(rhs);
+            },
+
+            ADD_HL_SP => {
+                let rhs = self.stack_pointer;
+                self.add_hl(rhs);
+            },
+
+            ADD_SP_r8 => {
+                let offset = self.get_next_byte();
+                self.stack_pointer = self.add_sp(offset);
+            },
+
+            AND_d8 => {
+                let rhs = self.get_next_byte();
+                self.and(rhs);
+            },
+
+            AND_HL => {
+                let rhs = self.byte_for_register_pair(HL);
+                self.and(rhs);
+            },
+
+            AND_r(r) => {
+                let rhs = self.registers[r];
+                self.and(rhs);
+            },
+
+            CALL_a16 => {
+                let address = self.get_next_two_bytes();
+                self.call(address);
+            },
+
+            CALL_cc_a16(cc) => {
+                let address = self.get_next_two_bytes();
+                if self.flags.check(cc) {
+                    self.call(address);
+                    return instruction.cycles(true);
+                }
+            },
+
+            CCF => {
+                self.flags.n = false;
+                self.flags.h = false;
+                self.flags.cy = !self.flags.cy;
+            },
+
+            CP_d8 => {
+                let lhs = self.registers.a;
+                let rhs = self.get_next_byte();
+                self.sub(lhs, rhs, false);
+            },
+
+            CP_HL => {
+                let lhs = self.registers.a;
+                let rhs = self.byte_for_register_pair(HL);
+                self.sub(lhs, rhs, false);
+            },
+
+            CP_r(r) => {
+                let lhs = self.registers.a;
+                let rhs = self.registers[r];
+                self.sub(lhs, rhs, false);
+            },
+
+            CPL => {
+                self.registers.a = !self.registers.a;
+                self.flags.n = true;
+                self.flags.h = true;
+            },
+
+            DAA => self.daa(),
+
+            DEC_HL => {
+                let address = self.registers.pair(HL);
+                let lhs = self.bus.read8(address);
+                let result = self.sub_core(lhs, 1, false);
+                self.bus.write8(address, result);
+            },
+
+            DEC_r(r) => {
+                let lhs = self.registers[r];
+                self.registers[r] = self.sub_core(lhs, 1, false);
+            },
+
+            DEC_rp(rp) => {
+                let value = self.registers.pair(rp).wrapping_sub(1);
+                self.registers.set_pair(rp, value);
+            },
+
+            DEC_SP => {
+                self.stack_pointer = self.stack_pointer.wrapping_sub(1);
+            },
+
+            DI => {
+                self.interrupt_master_enable = false;
+                self.ei_delay = 0;
+            },
+
+            EI => {
+                if !self.interrupt_master_enable { self.ei_delay = 2 }
+            },
+
+            // With IME clear and an interrupt already pending, HALT exits
+            // immediately and triggers the HALT bug instead.
+            HALT => {
+                if !self.interrupt_master_enable && self.bus.pending_interrupt().is_some() {
+                    self.halt_bug = true;
+                } else {
+                    self.halted = true;
+                }
+            },
+
+            INC_HL => {
+                let address = self.registers.pair(HL);
+                let lhs = self.bus.read8(address);
+                let result = self.add_core(lhs, 1, false) as u8;
+                self.bus.write8(address, result);
+            },
+
             INC_r(r) => {
                 let lhs = self.registers[r];
                 self.registers[r] = self.add_core(lhs, 1, false) as u8;
             },
 
+            INC_rp(rp) => {
+                let value = self.registers.pair(rp).wrapping_add(1);
+                self.registers.set_pair(rp, value);
+            },
+
+            INC_SP => {
+                self.stack_pointer = self.stack_pointer.wrapping_add(1);
+            },
+
+            JP_a16 => {
+                self.program_counter = self.get_next_two_bytes();
+            },
+
+            JP_cc_a16(cc) => {
+                let address = self.get_next_two_bytes();
+                if self.flags.check(cc) {
+                    self.program_counter = address;
+                    return instruction.cycles(true);
+                }
+            },
+
+            JP_HL => {
+                self.program_counter = self.registers.pair(HL);
+            },
+
+            JR_cc_r8(cc) => {
+                let offset = self.get_next_byte();
+                if self.flags.check(cc) {
+                    self.jump_relative(offset);
+                    return instruction.cycles(true);
+                }
+            },
+
+            JR_r8 => {
+                let offset = self.get_next_byte();
+                self.jump_relative(offset);
+            },
+
             LD_A_a8 => {
                 let address = internal_ram_address(self.get_next_byte());
-                self.registers.a = self.memory[address as usize];
+                self.registers.a = self.bus.read8(address);
             },
 
             LD_A_a16 => {
                 let address = self.get_next_two_bytes();
-                self.registers.a = self.memory[address as usize];
+                self.registers.a = self.bus.read8(address);
             },
 
             LD_A_C => {
                 let address = internal_ram_address(self.registers.c);
-                self.registers.a = self.memory[address as usize];
+                self.registers.a = self.bus.read8(address);
             },
 
             LD_A_HLD => {
                 let address = self.registers.pair(HL);
-                self.registers.a = self.memory[address as usize];
+                self.registers.a = self.bus.read8(address);
                 self.registers.set_pair(HL, address.wrapping_sub(1));
             },
 
             LD_A_HLI => {
                 let address = self.registers.pair(HL);
-                self.registers.a = self.memory[address as usize];
+                self.registers.a = self.bus.read8(address);
                 self.registers.set_pair(HL, address.wrapping_add(1));
             },
 
@@ -123,38 +506,51 @@ impl Cpu {
 
             LD_a8_A => {
                 let address = internal_ram_address(self.get_next_byte());
-                self.memory[address as usize] = self.registers.a;
+                self.bus.write8(address, self.registers.a);
             },
 
             LD_a16_A => {
                 let address = self.get_next_two_bytes();
-                self.memory[address as usize] = self.registers.a;
+                self.bus.write8(address, self.registers.a);
+            },
+
+            LD_a16_SP => {
+                let address = self.get_next_two_bytes();
+                let data = self.stack_pointer;
+                self.bus.write16(address, data);
             },
 
             LD_C_A => {
                 let address = internal_ram_address(self.registers.c);
-                self.memory[address as usize] = self.registers.a;
+                self.bus.write8(address, self.registers.a);
             },
 
             LD_HL_d8 => {
                 let address = self.registers.pair(HL);
-                self.memory[address as usize] = self.get_next_byte();
+                let value = self.get_next_byte();
+                self.bus.write8(address, value);
             },
 
             LD_HL_r(r) => {
                 let address = self.registers.pair(HL);
-                self.memory[address as usize] = self.registers[r];
+                self.bus.write8(address, self.registers[r]);
+            },
+
+            LD_HL_SP_r8 => {
+                let offset = self.get_next_byte();
+                let result = self.add_sp(offset);
+                self.registers.set_pair(HL, result);
             },
 
             LD_HLD_A => {
                 let address = self.registers.pair(HL);
-                self.memory[address as usize] = self.registers.a;
+                self.bus.write8(address, self.registers.a);
                 self.registers.set_pair(HL, address.wrapping_sub(1));
             },
 
             LD_HLI_A => {
                 let address = self.registers.pair(HL);
-                self.memory[address as usize] = self.registers.a;
+                self.bus.write8(address, self.registers.a);
                 self.registers.set_pair(HL, address.wrapping_add(1));
             },
 
@@ -162,14 +558,17 @@ impl Cpu {
                 self.registers[r] = self.get_next_byte();
             },
 
-
             LD_r_HL(r) => {
                 self.registers[r] = self.byte_for_register_pair(HL);
             },
 
+            LD_r_r(r1, r2) => {
+                self.registers[r1] = self.registers[r2];
+            },
+
             LD_rp_A(rp) => {
                 let address = self.registers.pair(rp);
-                self.memory[address as usize] = self.registers.a;
+                self.bus.write8(address, self.registers.a);
             },
 
             LD_rp_d16(rp) => {
@@ -187,11 +586,98 @@ impl Cpu {
 
             NOP => (),
 
+            OR_d8 => {
+                let rhs = self.get_next_byte();
+                self.or(rhs);
+            },
+
+            OR_HL => {
+                let rhs = self.byte_for_register_pair(HL);
+                self.or(rhs);
+            },
+
+            OR_r(r) => {
+                let rhs = self.registers[r];
+                self.or(rhs);
+            },
+
+            POP_AF => {
+                let data = self.pop();
+                self.registers.a = (data >> 8) as u8;
+                self.flags = Flags::from_byte(data as u8);
+            },
+
+            POP_rp(rp) => {
+                let data = self.pop();
+                self.registers.set_pair(rp, data);
+            },
+
+            PREFIX_CB => {
+                let byte = self.get_next_byte();
+                let instruction = CbInstruction::from_byte(byte);
+
+                if self.logging {
+                    log_cb_instruction(instruction, self.program_counter);
+                }
+                self.process_cb_instruction(instruction);
+                return PREFIX_CB.cycles(false) + instruction.cycles();
+            },
+
+            PUSH_AF => {
+                let data = ((self.registers.a as u16) << 8)
+                    + self.flags.to_byte() as u16;
+                self.push(data);
+            },
+
             PUSH_rp(rp) => {
                 let data = self.registers.pair(rp);
                 self.push(data);
             },
 
+            RET => {
+                self.program_counter = self.pop();
+            },
+
+            RET_cc(cc) => {
+                if self.flags.check(cc) {
+                    self.program_counter = self.pop();
+                    return instruction.cycles(true);
+                }
+            },
+
+            RETI => {
+                self.program_counter = self.pop();
+                self.interrupt_master_enable = true;
+            },
+
+            RLA => {
+                let value = self.registers.a;
+                self.registers.a = self.rotate_left(value, false);
+                self.flags.z = false;
+            },
+
+            RLCA => {
+                let value = self.registers.a;
+                self.registers.a = self.rotate_left(value, true);
+                self.flags.z = false;
+            },
+
+            RRA => {
+                let value = self.registers.a;
+                self.registers.a = self.rotate_right(value, false);
+                self.flags.z = false;
+            },
+
+            RRCA => {
+                let value = self.registers.a;
+                self.registers.a = self.rotate_right(value, true);
+                self.flags.z = false;
+            },
+
+            RST(address) => {
+                self.call(address as u16);
+            },
+
             SBC_A_d8 => {
                 let lhs = self.registers.a;
                 let rhs = self.get_next_byte();
@@ -210,6 +696,25 @@ impl Cpu {
                 self.registers.a = self.sub(lhs, rhs, true);
             },
 
+            SCF => {
+                self.flags.n = false;
+                self.flags.h = false;
+                self.flags.cy = true;
+            },
+
+            // STOP is encoded as 0x10 0x00; the second byte is skipped. It
+            // resets DIV, and on CGB arming KEY1 first turns it into a speed
+            // switch.
+            STOP => {
+                self.get_next_byte();
+                self.bus.write8(0xFF04, 0);
+                if self.bus.speed_switch_armed() {
+                    self.bus.switch_speed();
+                } else {
+                    self.stopped = true;
+                }
+            },
+
             SUB_d8 => {
                 let lhs = self.registers.a;
                 let rhs = self.get_next_byte();
@@ -228,19 +733,134 @@ impl Cpu {
                 self.registers.a = self.sub(lhs, rhs, false);
             },
 
+            XOR_d8 => {
+                let rhs = self.get_next_byte();
+                self.xor(rhs);
+            },
+
+            XOR_HL => {
+                let rhs = self.byte_for_register_pair(HL);
+                self.xor(rhs);
+            },
+
+            XOR_r(r) => {
+                let rhs = self.registers[r];
+                self.xor(rhs);
+            },
+
             Unknown => (),
         }
+
+        instruction.cycles(false)
+    }
+
+    fn process_cb_instruction(&mut self, instruction: CbInstruction) {
+        use instructions::CbInstruction::*;
+
+        match instruction {
+            BIT_b_HL(b) => {
+                let value = self.byte_for_register_pair(HL);
+                self.bit(b, value);
+            },
+
+            BIT_b_r(b, r) => {
+                let value = self.registers[r];
+                self.bit(b, value);
+            },
+
+            RES_b_HL(b) => {
+                let address = self.registers.pair(HL);
+                let value = self.bus.read8(address);
+                self.bus.write8(address, value & !(1 << b));
+            },
+
+            RES_b_r(b, r) => {
+                self.registers[r] &= !(1 << b);
+            },
+
+            RL_HL => self.update_byte_for_hl(|cpu, v| cpu.rotate_left(v, false)),
+
+            RL_r(r) => {
+                let value = self.registers[r];
+                self.registers[r] = self.rotate_left(value, false);
+            },
+
+            RLC_HL => self.update_byte_for_hl(|cpu, v| cpu.rotate_left(v, true)),
+
+            RLC_r(r) => {
+                let value = self.registers[r];
+                self.registers[r] = self.rotate_left(value, true);
+            },
+
+            RR_HL => self.update_byte_for_hl(|cpu, v| cpu.rotate_right(v, false)),
+
+            RR_r(r) => {
+                let value = self.registers[r];
+                self.registers[r] = self.rotate_right(value, false);
+            },
+
+            RRC_HL => self.update_byte_for_hl(|cpu, v| cpu.rotate_right(v, true)),
+
+            RRC_r(r) => {
+                let value = self.registers[r];
+                self.registers[r] = self.rotate_right(value, true);
+            },
+
+            SET_b_HL(b) => {
+                let address = self.registers.pair(HL);
+                let value = self.bus.read8(address);
+                self.bus.write8(address, value | (1 << b));
+            },
+
+            SET_b_r(b, r) => {
+                self.registers[r] |= 1 << b;
+            },
+
+            SLA_HL => self.update_byte_for_hl(Cpu::shift_left),
+
+            SLA_r(r) => {
+                let value = self.registers[r];
+                self.registers[r] = self.shift_left(value);
+            },
+
+            SRA_HL => self.update_byte_for_hl(|cpu, v| cpu.shift_right(v, true)),
+
+            SRA_r(r) => {
+                let value = self.registers[r];
+                self.registers[r] = self.shift_right(value, true);
+            },
+
+            SRL_HL => self.update_byte_for_hl(|cpu, v| cpu.shift_right(v, false)),
+
+            SRL_r(r) => {
+                let value = self.registers[r];
+                self.registers[r] = self.shift_right(value, false);
+            },
+
+            SWAP_HL => self.update_byte_for_hl(Cpu::swap),
+
+            SWAP_r(r) => {
+                let value = self.registers[r];
+                self.registers[r] = self.swap(value);
+            },
+        }
     }
 
     // -------------------------------------------------------------------------
 
     fn byte_for_register_pair(&self, rp: RegisterPair) -> u8 {
         let address = self.registers.pair(rp);
-        self.memory[address as usize]
+        self.bus.read8(address)
+    }
+
+    fn call(&mut self, address: u16) {
+        let return_address = self.program_counter;
+        self.push(return_address);
+        self.program_counter = address;
     }
 
     fn get_next_byte(&mut self) -> u8 {
-        let byte = self.memory[self.program_counter as usize];
+        let byte = self.bus.read8(self.program_counter);
         self.program_counter = self.program_counter.wrapping_add(1);
         byte
     }
@@ -251,13 +871,33 @@ impl Cpu {
         ((second_byte as u16) << 8) + first_byte as u16
     }
 
+    fn jump_relative(&mut self, offset: u8) {
+        let offset = offset as i8 as i16 as u16;
+        self.program_counter = self.program_counter.wrapping_add(offset);
+    }
+
+    fn pop(&mut self) -> u16 {
+        let second_byte = self.bus.read8(self.stack_pointer);
+        self.stack_pointer = self.stack_pointer.wrapping_add(1);
+        let first_byte = self.bus.read8(self.stack_pointer);
+        self.stack_pointer = self.stack_pointer.wrapping_add(1);
+        ((first_byte as u16) << 8) + second_byte as u16
+    }
+
     fn push(&mut self, data: u16) {
         let first_byte = (data >> 8) as u8;
         let second_byte = (data & 0xFF) as u8;
         self.stack_pointer = self.stack_pointer.wrapping_sub(1);
-        self.memory[self.stack_pointer as usize] = first_byte;
+        self.bus.write8(self.stack_pointer, first_byte);
         self.stack_pointer = self.stack_pointer.wrapping_sub(1);
-        self.memory[self.stack_pointer as usize] = second_byte;
+        self.bus.write8(self.stack_pointer, second_byte);
+    }
+
+    fn update_byte_for_hl(&mut self, operation: fn(&mut Cpu, u8) -> u8) {
+        let address = self.registers.pair(HL);
+        let value = self.bus.read8(address);
+        let result = operation(self, value);
+        self.bus.write8(address, result);
     }
 
     // -------------------------------------------------------------------------
@@ -277,10 +917,127 @@ impl Cpu {
         result
     }
 
+    fn add_hl(&mut self, rhs: u16) {
+        let lhs = self.registers.pair(HL);
+        let result = (lhs as u32) + (rhs as u32);
+        self.flags.h = (lhs & 0x0FFF) + (rhs & 0x0FFF) > 0x0FFF;
+        self.flags.n = false;
+        self.flags.cy = result > 0xFFFF;
+        self.registers.set_pair(HL, result as u16);
+    }
+
+    // Flags for ADD SP,r8 and LD HL,SP+r8 come from the unsigned addition of
+    // the offset to the low byte of SP.
+    fn add_sp(&mut self, offset: u8) -> u16 {
+        let lhs = self.stack_pointer;
+        let rhs = offset as i8 as i16 as u16;
+        self.flags.z = false;
+        self.flags.n = false;
+        self.flags.h = (lhs & 0x0F) + (rhs & 0x0F) > 0x0F;
+        self.flags.cy = (lhs & 0xFF) + (rhs & 0xFF) > 0xFF;
+        lhs.wrapping_add(rhs)
+    }
+
+    fn and(&mut self, rhs: u8) {
+        self.registers.a &= rhs;
+        let result = self.registers.a;
+        self.set_flag_z(result);
+        self.flags.n = false;
+        self.flags.h = true;
+        self.flags.cy = false;
+    }
+
+    fn bit(&mut self, b: u8, value: u8) {
+        self.set_flag_z(value & (1 << b));
+        self.flags.n = false;
+        self.flags.h = true;
+    }
+
+    // http://forums.nesdev.com/viewtopic.php?t=15944
+    fn daa(&mut self) {
+        let mut a = self.registers.a;
+
+        if !self.flags.n {
+            if self.flags.cy || a > 0x99 {
+                a = a.wrapping_add(0x60);
+                self.flags.cy = true;
+            }
+            if self.flags.h || (a & 0x0F) > 0x09 {
+                a = a.wrapping_add(0x06);
+            }
+        } else {
+            if self.flags.cy { a = a.wrapping_sub(0x60) }
+            if self.flags.h { a = a.wrapping_sub(0x06) }
+        }
+
+        self.registers.a = a;
+        self.set_flag_z(a);
+        self.flags.h = false;
+    }
+
+    fn or(&mut self, rhs: u8) {
+        self.registers.a |= rhs;
+        let result = self.registers.a;
+        self.set_flag_z(result);
+        self.flags.n = false;
+        self.flags.h = false;
+        self.flags.cy = false;
+    }
+
+    // With `circular` set the outgoing bit is rotated back in (RLC), otherwise
+    // the carry flag is (RL).
+    fn rotate_left(&mut self, value: u8, circular: bool) -> u8 {
+        let incoming = if circular { value >> 7 } else { self.flags.cy as u8 };
+        let result = (value << 1) | incoming;
+        self.set_flag_z(result);
+        self.flags.n = false;
+        self.flags.h = false;
+        self.flags.cy = value & 0x80 == 0x80;
+        result
+    }
+
+    fn rotate_right(&mut self, value: u8, circular: bool) -> u8 {
+        let incoming = if circular { value & 0x01 } else { self.flags.cy as u8 };
+        let result = (value >> 1) | (incoming << 7);
+        self.set_flag_z(result);
+        self.flags.n = false;
+        self.flags.h = false;
+        self.flags.cy = value & 0x01 == 0x01;
+        result
+    }
+
+    fn shift_left(&mut self, value: u8) -> u8 {
+        let result = value << 1;
+        self.set_flag_z(result);
+        self.flags.n = false;
+        self.flags.h = false;
+        self.flags.cy = value & 0x80 == 0x80;
+        result
+    }
+
+    // An arithmetic shift (SRA) keeps bit 7, a logical one (SRL) clears it.
+    fn shift_right(&mut self, value: u8, arithmetic: bool) -> u8 {
+        let result = if arithmetic {
+            (value >> 1) | (value & 0x80)
+        } else {
+            value >> 1
+        };
+        self.set_flag_z(result);
+        self.flags.n = false;
+        self.flags.h = false;
+        self.flags.cy = value & 0x01 == 0x01;
+        result
+    }
+
     fn sub(&mut self, lhs: u8, rhs: u8, carry: bool) -> u8 {
         let cy: u8 = if carry && self.flags.cy { 1 } else { 0 };
-        let result = lhs.wrapping_sub(rhs).wrapping_sub(cy);
         self.flags.cy = rhs as i16 > (lhs as i16) - (cy as i16);
+        self.sub_core(lhs, rhs, carry && cy == 1)
+    }
+
+    fn sub_core(&mut self, lhs: u8, rhs: u8, carry: bool) -> u8 {
+        let cy: u8 = if carry { 1 } else { 0 };
+        let result = lhs.wrapping_sub(rhs).wrapping_sub(cy);
         self.flags.h = ((lhs & 0xF)
             .wrapping_sub(rhs & 0xF)
             .wrapping_sub(cy))
@@ -290,6 +1047,24 @@ impl Cpu {
         result
     }
 
+    fn swap(&mut self, value: u8) -> u8 {
+        let result = value.rotate_left(4);
+        self.set_flag_z(result);
+        self.flags.n = false;
+        self.flags.h = false;
+        self.flags.cy = false;
+        result
+    }
+
+    fn xor(&mut self, rhs: u8) {
+        self.registers.a ^= rhs;
+        let result = self.registers.a;
+        self.set_flag_z(result);
+        self.flags.n = false;
+        self.flags.h = false;
+        self.flags.cy = false;
+    }
+
     // -------------------------------------------------------------------------
 
     fn set_flag_z(&mut self, result: u8) {
@@ -297,6 +1072,399 @@ impl Cpu {
     }
 }
 
+impl Default for Cpu {
+    fn default() -> Cpu {
+        Cpu::new()
+    }
+}
+
 fn internal_ram_address(offset: u8) -> u16 {
     0xFF00 + offset as u16
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mappers::RomOnly;
+
+    fn cpu_with_program(program: &[u8], handler: &[u8]) -> Cpu {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0040..0x0040 + handler.len()].copy_from_slice(handler);
+        rom[0x0100..0x0100 + program.len()].copy_from_slice(program);
+
+        let mut cpu = Cpu::new();
+        cpu.set_logging(false);
+        cpu.load_cartridge(Box::new(RomOnly::new(rom, 0)));
+        cpu.program_counter = 0x0100;
+        cpu.stack_pointer = 0xFFFE;
+        cpu
+    }
+
+    #[test]
+    fn ei_halt_with_pending_interrupt_returns_to_halt() {
+        // EI; HALT with a handler of LD A,0x42.
+        let mut cpu = cpu_with_program(&[0xFB, 0x76, 0x00], &[0x3E, 0x42]);
+        cpu.bus.write8(0xFFFF, 0x01);
+        cpu.bus.write8(0xFF0F, 0x01);
+
+        cpu.step();
+        cpu.step();
+        assert!(cpu.halt_bug);
+
+        cpu.step();
+        assert_eq!(cpu.program_counter, 0x0040);
+        assert_eq!(cpu.bus.read16(0xFFFC), 0x0101);
+        assert!(!cpu.halt_bug);
+
+        cpu.step();
+        assert_eq!(cpu.registers.a, 0x42);
+    }
+
+    #[test]
+    fn general_dma_stalls_cpu_for_each_block() {
+        // LD A,0x01; LDH (0x55),A; NOP, copying two blocks from 0x0100.
+        let program = [0x3E, 0x01, 0xE0, 0x55, 0x00];
+        for &double_speed in &[false, true] {
+            let mut cpu = cpu_with_program(&program, &[]);
+            cpu.bus.set_cgb_mode(true);
+            if double_speed { cpu.bus.switch_speed() }
+            cpu.bus.write8(0xFF51, 0x01);
+            cpu.bus.write8(0xFF52, 0x00);
+            cpu.bus.write8(0xFF53, 0x00);
+            cpu.bus.write8(0xFF54, 0x00);
+
+            let block = if double_speed { 16 } else { 8 };
+            let cycles: Vec<u32> = (0..5).map(|_| cpu.step()).collect();
+            assert_eq!(cycles, vec![2, 3, block, block, 1]);
+            assert_eq!(cpu.program_counter, 0x0105);
+            assert_eq!(cpu.ppu().read_vram(0x8000), 0x3E);
+        }
+    }
+
+    #[test]
+    fn halt_with_interrupts_disabled_wakes_without_dispatch() {
+        // DI; IE = timer; TAC = 0x05; HALT; LD A,0x42; JR -2
+        let program = [
+            0xF3, 0x3E, 0x04, 0xE0, 0xFF, 0x3E, 0x05, 0xE0, 0x07,
+            0x76, 0x3E, 0x42, 0x18, 0xFE,
+        ];
+        let mut cpu = cpu_with_program(&program, &[]);
+
+        match cpu.run() {
+            StopReason::InfiniteLoop(address) => assert_eq!(address, 0x010C),
+            reason => panic!("stopped with {:?}", reason),
+        }
+        assert_eq!(cpu.registers.a, 0x42);
+        assert_eq!(cpu.stack_pointer, 0xFFFE);
+        assert_eq!(cpu.bus.read8(0xFF0F) & 0x04, 0x04);
+        assert!(cpu.cycles() > 256 * 4);
+    }
+
+    #[test]
+    fn halt_bug_without_interrupt_enable_reads_next_byte_twice() {
+        // HALT; INC A; with IME off and an interrupt pending.
+        let mut cpu = cpu_with_program(&[0x76, 0x3C], &[]);
+        cpu.bus.write8(0xFFFF, 0x01);
+        cpu.bus.write8(0xFF0F, 0x01);
+
+        cpu.step();
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.registers.a, 2);
+        assert_eq!(cpu.program_counter, 0x0102);
+    }
+
+    fn run_program(program: &[u8]) -> Cpu {
+        let mut cpu = cpu_with_program(program, &[]);
+        while cpu.program_counter < 0x0100 + program.len() as u16 {
+            cpu.step();
+        }
+        cpu
+    }
+
+    fn flags(cpu: &Cpu) -> (bool, bool, bool, bool) {
+        (cpu.flags.z, cpu.flags.n, cpu.flags.h, cpu.flags.cy)
+    }
+
+    #[test]
+    fn daa_after_add() {
+        // LD A,0x45; ADD A,0x38; DAA
+        let cpu = run_program(&[0x3E, 0x45, 0xC6, 0x38, 0x27]);
+        assert_eq!(cpu.registers.a, 0x83);
+        assert_eq!(flags(&cpu), (false, false, false, false));
+
+        // LD A,0x99; ADD A,0x01; DAA
+        let cpu = run_program(&[0x3E, 0x99, 0xC6, 0x01, 0x27]);
+        assert_eq!(cpu.registers.a, 0x00);
+        assert_eq!(flags(&cpu), (true, false, false, true));
+    }
+
+    #[test]
+    fn daa_after_sub() {
+        // LD A,0x83; SUB 0x38; DAA
+        let cpu = run_program(&[0x3E, 0x83, 0xD6, 0x38, 0x27]);
+        assert_eq!(cpu.registers.a, 0x45);
+        assert_eq!(flags(&cpu), (false, true, false, false));
+
+        // LD A,0x10; SUB 0x20; DAA
+        let cpu = run_program(&[0x3E, 0x10, 0xD6, 0x20, 0x27]);
+        assert_eq!(cpu.registers.a, 0x90);
+        assert_eq!(flags(&cpu), (false, true, false, true));
+    }
+
+    #[test]
+    fn add_hl_half_carries_from_bit_11_and_keeps_z() {
+        // XOR A; LD HL,0x0FFF; LD BC,0x0001; ADD HL,BC
+        let cpu = run_program(&[0xAF, 0x21, 0xFF, 0x0F, 0x01, 0x01, 0x00, 0x09]);
+        assert_eq!(cpu.registers.pair(HL), 0x1000);
+        assert_eq!(flags(&cpu), (true, false, true, false));
+
+        // LD HL,0x8000; ADD HL,HL
+        let cpu = run_program(&[0x21, 0x00, 0x80, 0x29]);
+        assert_eq!(cpu.registers.pair(HL), 0x0000);
+        assert_eq!(flags(&cpu), (false, false, false, true));
+    }
+
+    #[test]
+    fn add_sp_takes_flags_from_low_byte() {
+        // LD SP,0x00FF; ADD SP,1
+        let cpu = run_program(&[0x31, 0xFF, 0x00, 0xE8, 0x01]);
+        assert_eq!(cpu.stack_pointer, 0x0100);
+        assert_eq!(flags(&cpu), (false, false, true, true));
+
+        // LD SP,0x0000; ADD SP,-1
+        let cpu = run_program(&[0x31, 0x00, 0x00, 0xE8, 0xFF]);
+        assert_eq!(cpu.stack_pointer, 0xFFFF);
+        assert_eq!(flags(&cpu), (false, false, false, false));
+    }
+
+    #[test]
+    fn ld_hl_sp_offset_takes_flags_from_low_byte() {
+        // XOR A; LD SP,0x000F; LD HL,SP+1
+        let cpu = run_program(&[0xAF, 0x31, 0x0F, 0x00, 0xF8, 0x01]);
+        assert_eq!(cpu.registers.pair(HL), 0x0010);
+        assert_eq!(cpu.stack_pointer, 0x000F);
+        assert_eq!(flags(&cpu), (false, false, true, false));
+    }
+
+    #[test]
+    fn adc_adds_carry_in() {
+        // SCF; LD A,0x0E; ADC A,0x01
+        let cpu = run_program(&[0x37, 0x3E, 0x0E, 0xCE, 0x01]);
+        assert_eq!(cpu.registers.a, 0x10);
+        assert_eq!(flags(&cpu), (false, false, true, false));
+
+        // SCF; LD A,0xFF; ADC A,0x00
+        let cpu = run_program(&[0x37, 0x3E, 0xFF, 0xCE, 0x00]);
+        assert_eq!(cpu.registers.a, 0x00);
+        assert_eq!(flags(&cpu), (true, false, true, true));
+    }
+
+    #[test]
+    fn sbc_subtracts_carry_in() {
+        // SCF; LD A,0x10; SBC A,0x0F
+        let cpu = run_program(&[0x37, 0x3E, 0x10, 0xDE, 0x0F]);
+        assert_eq!(cpu.registers.a, 0x00);
+        assert_eq!(flags(&cpu), (true, true, true, false));
+
+        // SCF; LD A,0x00; SBC A,0x00
+        let cpu = run_program(&[0x37, 0x3E, 0x00, 0xDE, 0x00]);
+        assert_eq!(cpu.registers.a, 0xFF);
+        assert_eq!(flags(&cpu), (false, true, true, true));
+    }
+
+    #[test]
+    fn inc_and_dec_leave_carry_untouched() {
+        // SCF; LD A,0xFF; INC A
+        let cpu = run_program(&[0x37, 0x3E, 0xFF, 0x3C]);
+        assert_eq!(cpu.registers.a, 0x00);
+        assert_eq!(flags(&cpu), (true, false, true, true));
+
+        // SCF; LD B,0x01; DEC B
+        let cpu = run_program(&[0x37, 0x06, 0x01, 0x05]);
+        assert_eq!(cpu.registers.b, 0x00);
+        assert_eq!(flags(&cpu), (true, true, false, true));
+
+        // XOR A; DEC A
+        let cpu = run_program(&[0xAF, 0x3D]);
+        assert_eq!(cpu.registers.a, 0xFF);
+        assert_eq!(flags(&cpu), (false, true, true, false));
+    }
+
+    #[test]
+    fn pop_af_masks_low_nibble_of_f() {
+        // LD BC,0x12FF; PUSH BC; POP AF; PUSH AF; POP DE
+        let cpu = run_program(&[0x01, 0xFF, 0x12, 0xC5, 0xF1, 0xF5, 0xD1]);
+        assert_eq!(cpu.registers.a, 0x12);
+        assert_eq!(flags(&cpu), (true, true, true, true));
+        assert_eq!(cpu.registers.pair(DE), 0x12F0);
+    }
+
+    #[test]
+    fn bit_on_hl_takes_12_cycles_and_keeps_carry() {
+        // LD HL,0xC000; SCF; BIT 7,(HL); BIT 0,(HL)
+        let mut cpu = cpu_with_program(&[0x21, 0x00, 0xC0, 0x37, 0xCB, 0x7E, 0xCB, 0x46], &[]);
+        cpu.bus.write8(0xC000, 0x01);
+        cpu.step();
+        cpu.step();
+
+        assert_eq!(cpu.step(), 3);
+        assert_eq!(flags(&cpu), (true, false, true, true));
+
+        assert_eq!(cpu.step(), 3);
+        assert_eq!(flags(&cpu), (false, false, true, true));
+    }
+
+    #[test]
+    fn res_and_set_on_hl_take_16_cycles() {
+        // LD HL,0xC000; RES 0,(HL); SET 7,(HL)
+        let mut cpu = cpu_with_program(&[0x21, 0x00, 0xC0, 0xCB, 0x86, 0xCB, 0xFE], &[]);
+        cpu.bus.write8(0xC000, 0x0F);
+        cpu.step();
+
+        assert_eq!(cpu.step(), 4);
+        assert_eq!(cpu.bus.read8(0xC000), 0x0E);
+
+        assert_eq!(cpu.step(), 4);
+        assert_eq!(cpu.bus.read8(0xC000), 0x8E);
+    }
+
+    #[test]
+    fn swap_clears_carry_and_sets_z_for_zero() {
+        // SCF; LD A,0xF1; SWAP A
+        let cpu = run_program(&[0x37, 0x3E, 0xF1, 0xCB, 0x37]);
+        assert_eq!(cpu.registers.a, 0x1F);
+        assert_eq!(flags(&cpu), (false, false, false, false));
+
+        // LD B,0x00; SWAP B
+        let cpu = run_program(&[0x06, 0x00, 0xCB, 0x30]);
+        assert_eq!(flags(&cpu), (true, false, false, false));
+    }
+
+    #[test]
+    fn sra_keeps_bit_7() {
+        // LD A,0x81; SRA A
+        let cpu = run_program(&[0x3E, 0x81, 0xCB, 0x2F]);
+        assert_eq!(cpu.registers.a, 0xC0);
+        assert_eq!(flags(&cpu), (false, false, false, true));
+
+        // LD A,0x81; SRL A
+        let cpu = run_program(&[0x3E, 0x81, 0xCB, 0x3F]);
+        assert_eq!(cpu.registers.a, 0x40);
+    }
+
+    #[test]
+    fn rlc_a_sets_z_but_rlca_does_not() {
+        // XOR A; RLC A
+        let cpu = run_program(&[0xAF, 0xCB, 0x07]);
+        assert_eq!(flags(&cpu), (true, false, false, false));
+
+        // XOR A; RLCA
+        let cpu = run_program(&[0xAF, 0x07]);
+        assert_eq!(flags(&cpu), (false, false, false, false));
+
+        // LD A,0x80; RLCA
+        let cpu = run_program(&[0x3E, 0x80, 0x07]);
+        assert_eq!(cpu.registers.a, 0x01);
+        assert_eq!(flags(&cpu), (false, false, false, true));
+    }
+
+    fn step_cycles(program: &[u8], steps: usize) -> Vec<u32> {
+        let mut cpu = cpu_with_program(program, &[]);
+        (0..steps).map(|_| cpu.step()).collect()
+    }
+
+    #[test]
+    fn conditional_jumps_cost_more_when_taken() {
+        // XOR A; JR Z,+0; JR NZ,+0; JP Z,0x0108; JP NZ,0x0000
+        let cycles = step_cycles(&[0xAF, 0x28, 0x00, 0x20, 0x00, 0xCA, 0x08, 0x01, 0xC2, 0x00, 0x00], 5);
+        assert_eq!(cycles, [1, 3, 2, 4, 3]);
+    }
+
+    #[test]
+    fn conditional_calls_and_returns_cost_more_when_taken() {
+        // XOR A; CALL NZ,0; CALL Z,0x0109; NOP; NOP; RET NZ; RET Z
+        let program = [0xAF, 0xC4, 0x00, 0x00, 0xCC, 0x09, 0x01, 0x00, 0x00, 0xC0, 0xC8];
+        let cycles = step_cycles(&program, 5);
+        assert_eq!(cycles, [1, 3, 6, 2, 5]);
+    }
+
+    #[test]
+    fn hl_operands_cost_extra_cycles() {
+        // LD HL,0xC000; LD (HL),0x01; INC (HL); ADD A,(HL); RLC B; RLC (HL)
+        let program = [0x21, 0x00, 0xC0, 0x36, 0x01, 0x34, 0x86, 0xCB, 0x00, 0xCB, 0x06];
+        let cycles = step_cycles(&program, 6);
+        assert_eq!(cycles, [3, 3, 3, 2, 2, 4]);
+    }
+
+    #[test]
+    fn cycles_accumulate() {
+        // NOP; LD BC,0x1234; PUSH BC; POP BC
+        let mut cpu = cpu_with_program(&[0x00, 0x01, 0x34, 0x12, 0xC5, 0xC1], &[]);
+        for _ in 0..4 { cpu.step(); }
+        assert_eq!(cpu.cycles(), 1 + 3 + 4 + 3);
+    }
+
+    // A CPU started without a boot ROM on a cartridge with the given CGB
+    // flag and header checksum.
+    fn cpu_after_boot(model: Model, cgb_flag: u8, header_checksum: u8) -> Cpu {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0143] = cgb_flag;
+        rom[0x014D] = header_checksum;
+
+        let mut cpu = Cpu::new();
+        cpu.set_logging(false);
+        cpu.load_cartridge(Box::new(RomOnly::new(rom, 0)));
+        cpu.skip_boot_rom(model);
+        cpu
+    }
+
+    fn register_bytes(cpu: &Cpu) -> [u8; 8] {
+        let r = &cpu.registers;
+        [r.a, cpu.flags.to_byte(), r.b, r.c, r.d, r.e, r.h, r.l]
+    }
+
+    #[test]
+    fn skip_boot_rom_sets_registers_for_each_model() {
+        let cases = [
+            (Model::Dmg0, 0x00, 0x12, [0x01, 0x00, 0xFF, 0x13, 0x00, 0xC1, 0x84, 0x03]),
+            (Model::Dmg,  0x00, 0x12, [0x01, 0xB0, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D]),
+            (Model::Dmg,  0x00, 0x00, [0x01, 0x80, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D]),
+            (Model::Mgb,  0x00, 0x12, [0xFF, 0xB0, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D]),
+            (Model::Sgb,  0x00, 0x12, [0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60]),
+            (Model::Cgb,  0x80, 0x12, [0x11, 0x80, 0x00, 0x00, 0xFF, 0x56, 0x00, 0x0D]),
+            (Model::Cgb,  0x00, 0x12, [0x11, 0x80, 0x00, 0x00, 0x00, 0x08, 0x00, 0x7C]),
+            (Model::Agb,  0x80, 0x12, [0x11, 0x00, 0x01, 0x00, 0xFF, 0x56, 0x00, 0x0D]),
+            (Model::Agb,  0x00, 0x12, [0x11, 0x00, 0x01, 0x00, 0x00, 0x08, 0x00, 0x7C]),
+        ];
+
+        for &(model, cgb_flag, header_checksum, registers) in &cases {
+            let cpu = cpu_after_boot(model, cgb_flag, header_checksum);
+            assert_eq!(register_bytes(&cpu), registers, "{:?}", model);
+            assert_eq!(cpu.stack_pointer, 0xFFFE);
+            assert_eq!(cpu.program_counter, 0x0100);
+            assert_eq!(cpu.bus.cgb_mode(), cgb_flag == 0x80, "{:?}", model);
+        }
+    }
+
+    #[test]
+    fn skip_boot_rom_sets_io_registers() {
+        let dmg = cpu_after_boot(Model::Dmg, 0x00, 0x12);
+        assert_eq!(dmg.bus.read8(0xFF04), 0xAB);
+        assert_eq!(dmg.bus.read8(0xFF0F), 0xE1);
+        assert_eq!(dmg.bus.read8(0xFF26), 0xF1);
+        assert_eq!(dmg.bus.read8(0xFF40), 0x91);
+        assert_eq!(dmg.bus.read8(0xFF46), 0xFF);
+        assert_eq!(dmg.bus.read8(0xFF47), 0xFC);
+
+        let dmg0 = cpu_after_boot(Model::Dmg0, 0x00, 0x12);
+        assert_eq!(dmg0.bus.read8(0xFF04), 0x18);
+
+        let sgb = cpu_after_boot(Model::Sgb, 0x00, 0x12);
+        assert_eq!(sgb.bus.read8(0xFF26), 0xF0);
+
+        let cgb = cpu_after_boot(Model::Cgb, 0x80, 0x12);
+        assert_eq!(cgb.bus.read8(0xFF02), 0x7F);
+        assert_eq!(cgb.bus.read8(0xFF46), 0x00);
+    }
+}
@@ -1,108 +1,200 @@
-use instructions::{Instruction, Instruction::*};
+use bus::Bus;
+use instructions::{CbInstruction, Instruction, Instruction::*};
 use std::fmt::{Display, Formatter, Result};
 
-pub fn log_instruction(instruction: Instruction,
-                       memory: &Vec<u8>,
-                       program_counter: u16) {
+pub fn log_instruction<B: Bus>(instruction: Instruction,
+                               bus: &B,
+                               program_counter: u16) {
 
-    if let NOP     = instruction { return }
-    if let Unknown = instruction { return }
+    if let NOP       = instruction { return }
+    if let PREFIX_CB = instruction { return }
+    if let Unknown   = instruction { return }
 
     let address = format!("{:#06X}", program_counter.wrapping_sub(1));
     print!("{}\t{}", address, instruction);
 
+    if instruction.is_a8_instruction() {
+        let a8 = bus.read8(program_counter);
+        print!(" \t({:#06X})", 0xFF00 + a8 as u16);
+    }
+
     if instruction.is_a16_instruction() {
-        let a16 = get_next_two_bytes(memory, program_counter);
+        let a16 = bus.read16(program_counter);
         print!(" \t({:#06X})", a16);
     }
 
     if instruction.is_d8_instruction() {
-        let d8 = memory[program_counter as usize];
+        let d8 = bus.read8(program_counter);
         print!(" \t{:#04X}", d8);
     }
 
     if instruction.is_d16_instruction() {
-        let d16 = get_next_two_bytes(memory, program_counter);
+        let d16 = bus.read16(program_counter);
         print!(" \t{:#06X}", d16);
     }
 
+    if instruction.is_r8_instruction() {
+        let r8 = bus.read8(program_counter) as i8;
+        print!(" \t{}", r8);
+    }
+
     println!();
 }
 
-fn get_next_two_bytes(memory: &Vec<u8>, program_counter: u16) -> u16 {
-    let first_byte = memory[program_counter as usize];
-    let second_byte = memory[program_counter.wrapping_add(1) as usize];
-    ((second_byte as u16) << 8) + first_byte as u16
+pub fn log_cb_instruction(instruction: CbInstruction, program_counter: u16) {
+    let address = format!("{:#06X}", program_counter.wrapping_sub(2));
+    println!("{}\t{}", address, instruction);
 }
 
 impl Instruction {
+    pub fn is_a8_instruction(self) -> bool {
+        matches!(self, LD_A_a8 | LD_a8_A)
+    }
+
     pub fn is_a16_instruction(self) -> bool {
-        match self {
-            LD_A_a16 => true,
-            LD_a16_A => true,
-            _ => false,
-        }
+        matches!(self,
+            CALL_a16 | CALL_cc_a16(_) |
+            JP_a16 | JP_cc_a16(_) |
+            LD_A_a16 | LD_a16_A | LD_a16_SP)
     }
 
     pub fn is_d8_instruction(self) -> bool {
-        match self {
-            ADC_A_d8   => true,
-            ADD_A_d8   => true,
-            LD_HL_d8   => true,
-            LD_r_d8(_) => true,
-            SBC_A_d8   => true,
-            SUB_d8     => true,
-            _ => false,
-        }
+        matches!(self,
+            ADC_A_d8 | ADD_A_d8 | AND_d8 | CP_d8 |
+            LD_HL_d8 | LD_r_d8(_) | OR_d8 |
+            SBC_A_d8 | SUB_d8 | XOR_d8)
     }
 
     pub fn is_d16_instruction(self) -> bool {
-        match self {
-            LD_rp_d16(_) => true,
-            LD_SP_d16    => true,
-            _ => false,
-        }
+        matches!(self, LD_rp_d16(_) | LD_SP_d16)
+    }
+
+    pub fn is_r8_instruction(self) -> bool {
+        matches!(self, ADD_SP_r8 | JR_cc_r8(_) | JR_r8 | LD_HL_SP_r8)
     }
 }
 
 impl Display for Instruction {
     fn fmt(&self, f: &mut Formatter) -> Result {
         match *self {
-            ADC_A_d8      => write!(f, "ADC A,d8"),
-            ADC_A_HL      => write!(f, "ADC A,(HL)"),
-            ADC_A_r(r)    => write!(f, "ADC A,{:?}",   r),
-            ADD_A_d8      => write!(f, "ADD A,d8"),
-            ADD_A_HL      => write!(f, "ADD A,(HL)"),
-            ADD_A_r(r)    => write!(f, "ADD A,{:?}",   r),
-            INC_r(r)      => write!(f, "INC {:?}",     r),
-            LD_A_a8       => write!(f, "LD A,(a8)"),
-            LD_A_a16      => write!(f, "LD A,(a16)"),
-            LD_A_C        => write!(f, "LD A,(C)"),
-            LD_A_HLD      => write!(f, "LD A,(HLD)"),
-            LD_A_HLI      => write!(f, "LD A,(HLI)"),
-            LD_A_rp(rp)   => write!(f, "LD A,({:?})",  rp),
-            LD_a8_A       => write!(f, "LD (a8),A"),
-            LD_a16_A      => write!(f, "LD (a16),A"),
-            LD_C_A        => write!(f, "LD (C),A"),
-            LD_HL_d8      => write!(f, "LD (HL),d8"),
-            LD_HL_r(r)    => write!(f, "LD (HL),{:?}", r),
-            LD_HLD_A      => write!(f, "LD (HLD),A"),
-            LD_HLI_A      => write!(f, "LD (HLI),A"),
-            LD_r_d8(r)    => write!(f, "LD {:?},d8",   r),
-            LD_r_HL(r)    => write!(f, "LD {:?},(HL)", r),
-            LD_rp_A(rp)   => write!(f, "LD ({:?}),A",  rp),
-            LD_rp_d16(rp) => write!(f, "LD {:?},d16",  rp),
-            LD_SP_d16     => write!(f, "LD SP,d16"),
-            LD_SP_HL      => write!(f, "LD SP,HL"),
-            NOP           => write!(f, "NOP"),
-            PUSH_rp(rp)   => write!(f, "PUSH {:?}",    rp),
-            SBC_A_d8      => write!(f, "SBC A,d8"),
-            SBC_A_HL      => write!(f, "SBC A,(HL)"),
-            SBC_A_r(r)    => write!(f, "SBC A,{:?}",   r),
-            SUB_d8        => write!(f, "SUB A,d8"),
-            SUB_HL        => write!(f, "SUB (HL)"),
-            SUB_r(r)      => write!(f, "SUB {:?}",     r),
-            Unknown       => write!(f, "NOT IMPLEMENTED"),
+            ADC_A_d8        => write!(f, "ADC A,d8"),
+            ADC_A_HL        => write!(f, "ADC A,(HL)"),
+            ADC_A_r(r)      => write!(f, "ADC A,{:?}",   r),
+            ADD_A_d8        => write!(f, "ADD A,d8"),
+            ADD_A_HL        => write!(f, "ADD A,(HL)"),
+            ADD_A_r(r)      => write!(f, "ADD A,{:?}",   r),
+            ADD_HL_rp(rp)   => write!(f, "ADD HL,{:?}",  rp),
+            ADD_HL_SP       => write!(f, "ADD HL,SP"),
+            ADD_SP_r8       => write!(f, "ADD SP,r8"),
+            AND_d8          => write!(f, "AND d8"),
+            AND_HL          => write!(f, "AND (HL)"),
+            AND_r(r)        => write!(f, "AND {:?}",     r),
+            CALL_a16        => write!(f, "CALL a16"),
+            CALL_cc_a16(cc) => write!(f, "CALL {:?},a16", cc),
+            CCF             => write!(f, "CCF"),
+            CP_d8           => write!(f, "CP d8"),
+            CP_HL           => write!(f, "CP (HL)"),
+            CP_r(r)         => write!(f, "CP {:?}",      r),
+            CPL             => write!(f, "CPL"),
+            DAA             => write!(f, "DAA"),
+            DEC_HL          => write!(f, "DEC (HL)"),
+            DEC_r(r)        => write!(f, "DEC {:?}",     r),
+            DEC_rp(rp)      => write!(f, "DEC {:?}",     rp),
+            DEC_SP          => write!(f, "DEC SP"),
+            DI              => write!(f, "DI"),
+            EI              => write!(f, "EI"),
+            HALT            => write!(f, "HALT"),
+            INC_HL          => write!(f, "INC (HL)"),
+            INC_r(r)        => write!(f, "INC {:?}",     r),
+            INC_rp(rp)      => write!(f, "INC {:?}",     rp),
+            INC_SP          => write!(f, "INC SP"),
+            JP_a16          => write!(f, "JP a16"),
+            JP_cc_a16(cc)   => write!(f, "JP {:?},a16",  cc),
+            JP_HL           => write!(f, "JP (HL)"),
+            JR_cc_r8(cc)    => write!(f, "JR {:?},r8",   cc),
+            JR_r8           => write!(f, "JR r8"),
+            LD_A_a8         => write!(f, "LD A,(a8)"),
+            LD_A_a16        => write!(f, "LD A,(a16)"),
+            LD_A_C          => write!(f, "LD A,(C)"),
+            LD_A_HLD        => write!(f, "LD A,(HLD)"),
+            LD_A_HLI        => write!(f, "LD A,(HLI)"),
+            LD_A_rp(rp)     => write!(f, "LD A,({:?})",  rp),
+            LD_a8_A         => write!(f, "LD (a8),A"),
+            LD_a16_A        => write!(f, "LD (a16),A"),
+            LD_a16_SP       => write!(f, "LD (a16),SP"),
+            LD_C_A          => write!(f, "LD (C),A"),
+            LD_HL_d8        => write!(f, "LD (HL),d8"),
+            LD_HL_r(r)      => write!(f, "LD (HL),{:?}", r),
+            LD_HL_SP_r8     => write!(f, "LD HL,SP+r8"),
+            LD_HLD_A        => write!(f, "LD (HLD),A"),
+            LD_HLI_A        => write!(f, "LD (HLI),A"),
+            LD_r_d8(r)      => write!(f, "LD {:?},d8",   r),
+            LD_r_HL(r)      => write!(f, "LD {:?},(HL)", r),
+            LD_r_r(r1, r2)  => write!(f, "LD {:?},{:?}", r1, r2),
+            LD_rp_A(rp)     => write!(f, "LD ({:?}),A",  rp),
+            LD_rp_d16(rp)   => write!(f, "LD {:?},d16",  rp),
+            LD_SP_d16       => write!(f, "LD SP,d16"),
+            LD_SP_HL        => write!(f, "LD SP,HL"),
+            NOP             => write!(f, "NOP"),
+            OR_d8           => write!(f, "OR d8"),
+            OR_HL           => write!(f, "OR (HL)"),
+            OR_r(r)         => write!(f, "OR {:?}",      r),
+            POP_AF          => write!(f, "POP AF"),
+            POP_rp(rp)      => write!(f, "POP {:?}",     rp),
+            PREFIX_CB       => write!(f, "PREFIX CB"),
+            PUSH_AF         => write!(f, "PUSH AF"),
+            PUSH_rp(rp)     => write!(f, "PUSH {:?}",    rp),
+            RET             => write!(f, "RET"),
+            RET_cc(cc)      => write!(f, "RET {:?}",     cc),
+            RETI            => write!(f, "RETI"),
+            RLA             => write!(f, "RLA"),
+            RLCA            => write!(f, "RLCA"),
+            RRA             => write!(f, "RRA"),
+            RRCA            => write!(f, "RRCA"),
+            RST(n)          => write!(f, "RST {:02X}H",  n),
+            SBC_A_d8        => write!(f, "SBC A,d8"),
+            SBC_A_HL        => write!(f, "SBC A,(HL)"),
+            SBC_A_r(r)      => write!(f, "SBC A,{:?}",   r),
+            SCF             => write!(f, "SCF"),
+            STOP            => write!(f, "STOP"),
+            SUB_d8          => write!(f, "SUB A,d8"),
+            SUB_HL          => write!(f, "SUB (HL)"),
+            SUB_r(r)        => write!(f, "SUB {:?}",     r),
+            XOR_d8          => write!(f, "XOR d8"),
+            XOR_HL          => write!(f, "XOR (HL)"),
+            XOR_r(r)        => write!(f, "XOR {:?}",     r),
+            Unknown         => write!(f, "NOT IMPLEMENTED"),
+        }
+    }
+}
+
+impl Display for CbInstruction {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        use instructions::CbInstruction::*;
+
+        match *self {
+            BIT_b_HL(b)    => write!(f, "BIT {},(HL)",   b),
+            BIT_b_r(b, r)  => write!(f, "BIT {},{:?}",   b, r),
+            RES_b_HL(b)    => write!(f, "RES {},(HL)",   b),
+            RES_b_r(b, r)  => write!(f, "RES {},{:?}",   b, r),
+            RL_HL          => write!(f, "RL (HL)"),
+            RL_r(r)        => write!(f, "RL {:?}",       r),
+            RLC_HL         => write!(f, "RLC (HL)"),
+            RLC_r(r)       => write!(f, "RLC {:?}",      r),
+            RR_HL          => write!(f, "RR (HL)"),
+            RR_r(r)        => write!(f, "RR {:?}",       r),
+            RRC_HL         => write!(f, "RRC (HL)"),
+            RRC_r(r)       => write!(f, "RRC {:?}",      r),
+            SET_b_HL(b)    => write!(f, "SET {},(HL)",   b),
+            SET_b_r(b, r)  => write!(f, "SET {},{:?}",   b, r),
+            SLA_HL         => write!(f, "SLA (HL)"),
+            SLA_r(r)       => write!(f, "SLA {:?}",      r),
+            SRA_HL         => write!(f, "SRA (HL)"),
+            SRA_r(r)       => write!(f, "SRA {:?}",      r),
+            SRL_HL         => write!(f, "SRL (HL)"),
+            SRL_r(r)       => write!(f, "SRL {:?}",      r),
+            SWAP_HL        => write!(f, "SWAP (HL)"),
+            SWAP_r(r)      => write!(f, "SWAP {:?}",     r),
         }
     }
 }
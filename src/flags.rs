@@ -1,3 +1,8 @@
+use self::Condition::*;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Condition { NZ, Z, NC, C }
+
 #[derive(Debug)]
 pub struct Flags {
     pub z:  bool,
@@ -15,4 +20,37 @@ impl Flags {
             cy: false,
         }
     }
+
+    pub fn from_byte(byte: u8) -> Flags {
+        Flags {
+            z:  byte & 0x80 == 0x80,
+            n:  byte & 0x40 == 0x40,
+            h:  byte & 0x20 == 0x20,
+            cy: byte & 0x10 == 0x10,
+        }
+    }
+
+    pub fn to_byte(&self) -> u8 {
+        let mut byte = 0;
+        if self.z  { byte |= 0x80 }
+        if self.n  { byte |= 0x40 }
+        if self.h  { byte |= 0x20 }
+        if self.cy { byte |= 0x10 }
+        byte
+    }
+
+    pub fn check(&self, condition: Condition) -> bool {
+        match condition {
+            NZ => !self.z,
+            Z  => self.z,
+            NC => !self.cy,
+            C  => self.cy,
+        }
+    }
+}
+
+impl Default for Flags {
+    fn default() -> Flags {
+        Flags::new()
+    }
 }
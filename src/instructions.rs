@@ -1,7 +1,8 @@
     use self::Instruction::*;
+    use flags::Condition;
     use registers::*;
 
-    #[allow(non_camel_case_types)]
+    #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
     #[derive(Clone, Copy)]
     pub enum Instruction {
         ADC_A_d8,
@@ -10,7 +11,36 @@
         ADD_A_d8,
         ADD_A_HL,
         ADD_A_r(Register),
+        ADD_HL_rp(RegisterPair),
+        ADD_HL_SP,
+        ADD_SP_r8,
+        AND_d8,
+        AND_HL,
+        AND_r(Register),
+        CALL_a16,
+        CALL_cc_a16(Condition),
+        CCF,
+        CP_d8,
+        CP_HL,
+        CP_r(Register),
+        CPL,
+        DAA,
+        DEC_HL,
+        DEC_r(Register),
+        DEC_rp(RegisterPair),
+        DEC_SP,
+        DI,
+        EI,
+        HALT,
+        INC_HL,
         INC_r(Register),
+        INC_rp(RegisterPair),
+        INC_SP,
+        JP_a16,
+        JP_cc_a16(Condition),
+        JP_HL,
+        JR_cc_r8(Condition),
+        JR_r8,
         LD_A_a8,
         LD_A_a16,
         LD_A_C,
@@ -19,122 +49,334 @@
         LD_A_rp(RegisterPair),
         LD_a8_A,
         LD_a16_A,
+        LD_a16_SP,
         LD_C_A,
         LD_HL_d8,
         LD_HL_r(Register),
+        LD_HL_SP_r8,
         LD_HLD_A,
         LD_HLI_A,
         LD_r_d8(Register),
         LD_r_HL(Register),
+        LD_r_r(Register, Register),
         LD_rp_A(RegisterPair),
         LD_rp_d16(RegisterPair),
         LD_SP_d16,
         LD_SP_HL,
         NOP,
+        OR_d8,
+        OR_HL,
+        OR_r(Register),
+        POP_AF,
+        POP_rp(RegisterPair),
+        PREFIX_CB,
+        PUSH_AF,
         PUSH_rp(RegisterPair),
+        RET,
+        RET_cc(Condition),
+        RETI,
+        RLA,
+        RLCA,
+        RRA,
+        RRCA,
+        RST(u8),
         SBC_A_d8,
         SBC_A_HL,
         SBC_A_r(Register),
+        SCF,
+        STOP,
         SUB_d8,
         SUB_HL,
         SUB_r(Register),
+        XOR_d8,
+        XOR_HL,
+        XOR_r(Register),
         Unknown,
     }
 
     impl Instruction {
         pub fn from_byte(byte: u8) -> Instruction {
+            use flags::Condition::{NC, NZ, Z};
             use registers::{Register::*, RegisterPair::*};
 
             match byte {
                 0x00 => NOP,
                 0x01 => LD_rp_d16(BC),
                 0x02 => LD_rp_A(BC),
+                0x03 => INC_rp(BC),
                 0x04 => INC_r(B),
+                0x05 => DEC_r(B),
                 0x06 => LD_r_d8(B),
+                0x07 => RLCA,
+                0x08 => LD_a16_SP,
+                0x09 => ADD_HL_rp(BC),
                 0x0A => LD_A_rp(BC),
+                0x0B => DEC_rp(BC),
                 0x0C => INC_r(C),
+                0x0D => DEC_r(C),
                 0x0E => LD_r_d8(C),
+                0x0F => RRCA,
+                0x10 => STOP,
                 0x11 => LD_rp_d16(DE),
                 0x12 => LD_rp_A(DE),
+                0x13 => INC_rp(DE),
                 0x14 => INC_r(D),
+                0x15 => DEC_r(D),
                 0x16 => LD_r_d8(D),
+                0x17 => RLA,
+                0x18 => JR_r8,
+                0x19 => ADD_HL_rp(DE),
                 0x1A => LD_A_rp(DE),
+                0x1B => DEC_rp(DE),
                 0x1C => INC_r(E),
+                0x1D => DEC_r(E),
                 0x1E => LD_r_d8(E),
+                0x1F => RRA,
+                0x20 => JR_cc_r8(NZ),
                 0x21 => LD_rp_d16(HL),
                 0x22 => LD_HLI_A,
+                0x23 => INC_rp(HL),
                 0x24 => INC_r(H),
+                0x25 => DEC_r(H),
                 0x26 => LD_r_d8(H),
+                0x27 => DAA,
+                0x28 => JR_cc_r8(Z),
+                0x29 => ADD_HL_rp(HL),
                 0x2A => LD_A_HLI,
+                0x2B => DEC_rp(HL),
                 0x2C => INC_r(L),
+                0x2D => DEC_r(L),
                 0x2E => LD_r_d8(L),
+                0x2F => CPL,
+                0x30 => JR_cc_r8(NC),
                 0x31 => LD_SP_d16,
                 0x32 => LD_HLD_A,
+                0x33 => INC_SP,
+                0x34 => INC_HL,
+                0x35 => DEC_HL,
                 0x36 => LD_HL_d8,
+                0x37 => SCF,
+                0x38 => JR_cc_r8(Condition::C),
+                0x39 => ADD_HL_SP,
                 0x3A => LD_A_HLD,
+                0x3B => DEC_SP,
                 0x3C => INC_r(A),
+                0x3D => DEC_r(A),
                 0x3E => LD_r_d8(A),
-                0x46 => LD_r_HL(B),
-                0x4E => LD_r_HL(C),
-                0x56 => LD_r_HL(D),
-                0x5E => LD_r_HL(E),
-                0x66 => LD_r_HL(H),
-                0x6E => LD_r_HL(L),
-                0x70 => LD_HL_r(B),
-                0x71 => LD_HL_r(C),
-                0x72 => LD_HL_r(D),
-                0x73 => LD_HL_r(E),
-                0x74 => LD_HL_r(H),
-                0x75 => LD_HL_r(L),
-                0x77 => LD_HL_r(A),
-                0x7E => LD_r_HL(A),
-                0x80 => ADD_A_r(B),
-                0x81 => ADD_A_r(C),
-                0x82 => ADD_A_r(D),
-                0x83 => ADD_A_r(E),
-                0x84 => ADD_A_r(H),
-                0x85 => ADD_A_r(L),
-                0x86 => ADD_A_HL,
-                0x87 => ADD_A_r(A),
-                0x88 => ADC_A_r(B),
-                0x89 => ADC_A_r(C),
-                0x8A => ADC_A_r(D),
-                0x8B => ADC_A_r(E),
-                0x8C => ADC_A_r(H),
-                0x8D => ADC_A_r(L),
-                0x8E => ADC_A_HL,
-                0x8F => ADC_A_r(A),
-                0x90 => SUB_r(B),
-                0x91 => SUB_r(C),
-                0x92 => SUB_r(D),
-                0x93 => SUB_r(E),
-                0x94 => SUB_r(H),
-                0x95 => SUB_r(L),
-                0x96 => SUB_HL,
-                0x97 => SUB_r(A),
-                0x98 => SBC_A_r(B),
-                0x99 => SBC_A_r(C),
-                0x9A => SBC_A_r(D),
-                0x9B => SBC_A_r(E),
-                0x9C => SBC_A_r(H),
-                0x9D => SBC_A_r(L),
-                0x9E => SBC_A_HL,
-                0x9F => SBC_A_r(A),
+                0x3F => CCF,
+                0x76 => HALT,
+                0x40..=0x7F => {
+                    let destination = register_for_bits(byte >> 3);
+                    let source = register_for_bits(byte);
+
+                    match (destination, source) {
+                        (Some(d), Some(s)) => LD_r_r(d, s),
+                        (Some(d), None)    => LD_r_HL(d),
+                        (None, Some(s))    => LD_HL_r(s),
+                        (None, None)       => HALT,
+                    }
+                },
+                0x80..=0xBF => {
+                    let source = register_for_bits(byte);
+
+                    match ((byte >> 3) & 0x07, source) {
+                        (0, Some(r)) => ADD_A_r(r),
+                        (0, None)    => ADD_A_HL,
+                        (1, Some(r)) => ADC_A_r(r),
+                        (1, None)    => ADC_A_HL,
+                        (2, Some(r)) => SUB_r(r),
+                        (2, None)    => SUB_HL,
+                        (3, Some(r)) => SBC_A_r(r),
+                        (3, None)    => SBC_A_HL,
+                        (4, Some(r)) => AND_r(r),
+                        (4, None)    => AND_HL,
+                        (5, Some(r)) => XOR_r(r),
+                        (5, None)    => XOR_HL,
+                        (6, Some(r)) => OR_r(r),
+                        (6, None)    => OR_HL,
+                        (_, Some(r)) => CP_r(r),
+                        (_, None)    => CP_HL,
+                    }
+                },
+                0xC0 => RET_cc(NZ),
+                0xC1 => POP_rp(BC),
+                0xC2 => JP_cc_a16(NZ),
+                0xC3 => JP_a16,
+                0xC4 => CALL_cc_a16(NZ),
                 0xC5 => PUSH_rp(BC),
                 0xC6 => ADD_A_d8,
+                0xC7 => RST(0x00),
+                0xC8 => RET_cc(Z),
+                0xC9 => RET,
+                0xCA => JP_cc_a16(Z),
+                0xCB => PREFIX_CB,
+                0xCC => CALL_cc_a16(Z),
+                0xCD => CALL_a16,
                 0xCE => ADC_A_d8,
+                0xCF => RST(0x08),
+                0xD0 => RET_cc(NC),
+                0xD1 => POP_rp(DE),
+                0xD2 => JP_cc_a16(NC),
+                0xD4 => CALL_cc_a16(NC),
                 0xD5 => PUSH_rp(DE),
                 0xD6 => SUB_d8,
+                0xD7 => RST(0x10),
+                0xD8 => RET_cc(Condition::C),
+                0xD9 => RETI,
+                0xDA => JP_cc_a16(Condition::C),
+                0xDC => CALL_cc_a16(Condition::C),
                 0xDE => SBC_A_d8,
+                0xDF => RST(0x18),
                 0xE0 => LD_a8_A,
+                0xE1 => POP_rp(HL),
                 0xE2 => LD_C_A,
                 0xE5 => PUSH_rp(HL),
+                0xE6 => AND_d8,
+                0xE7 => RST(0x20),
+                0xE8 => ADD_SP_r8,
+                0xE9 => JP_HL,
                 0xEA => LD_a16_A,
+                0xEE => XOR_d8,
+                0xEF => RST(0x28),
                 0xF0 => LD_A_a8,
+                0xF1 => POP_AF,
                 0xF2 => LD_A_C,
+                0xF3 => DI,
+                0xF5 => PUSH_AF,
+                0xF6 => OR_d8,
+                0xF7 => RST(0x30),
+                0xF8 => LD_HL_SP_r8,
                 0xF9 => LD_SP_HL,
                 0xFA => LD_A_a16,
+                0xFB => EI,
+                0xFE => CP_d8,
+                0xFF => RST(0x38),
                 _ => Unknown,
             }
         }
+
+        // Machine cycles taken, including the opcode fetch. Conditional
+        // branches take longer when the branch is taken.
+        pub fn cycles(self, branch_taken: bool) -> u32 {
+            match self {
+                CALL_cc_a16(_) if branch_taken => 6,
+                JP_cc_a16(_)   if branch_taken => 4,
+                JR_cc_r8(_)    if branch_taken => 3,
+                RET_cc(_)      if branch_taken => 5,
+
+                CALL_a16 => 6,
+
+                LD_a16_SP => 5,
+
+                ADD_SP_r8 | JP_a16 | LD_A_a16 | LD_a16_A |
+                PUSH_AF | PUSH_rp(_) | RET | RETI | RST(_) => 4,
+
+                CALL_cc_a16(_) | DEC_HL | INC_HL | JP_cc_a16(_) | JR_r8 |
+                LD_A_a8 | LD_a8_A | LD_HL_d8 | LD_HL_SP_r8 |
+                LD_rp_d16(_) | LD_SP_d16 | POP_AF | POP_rp(_) => 3,
+
+                ADC_A_d8 | ADC_A_HL | ADD_A_d8 | ADD_A_HL |
+                ADD_HL_rp(_) | ADD_HL_SP | AND_d8 | AND_HL |
+                CP_d8 | CP_HL | DEC_rp(_) | DEC_SP | INC_rp(_) | INC_SP |
+                JR_cc_r8(_) | LD_A_C | LD_A_HLD | LD_A_HLI | LD_A_rp(_) |
+                LD_C_A | LD_HL_r(_) | LD_HLD_A | LD_HLI_A | LD_r_d8(_) |
+                LD_r_HL(_) | LD_rp_A(_) | LD_SP_HL | OR_d8 | OR_HL |
+                RET_cc(_) | SBC_A_d8 | SBC_A_HL | SUB_d8 | SUB_HL |
+                XOR_d8 | XOR_HL => 2,
+
+                _ => 1,
+            }
+        }
+    }
+
+    #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+    #[derive(Clone, Copy)]
+    pub enum CbInstruction {
+        BIT_b_HL(u8),
+        BIT_b_r(u8, Register),
+        RES_b_HL(u8),
+        RES_b_r(u8, Register),
+        RL_HL,
+        RL_r(Register),
+        RLC_HL,
+        RLC_r(Register),
+        RR_HL,
+        RR_r(Register),
+        RRC_HL,
+        RRC_r(Register),
+        SET_b_HL(u8),
+        SET_b_r(u8, Register),
+        SLA_HL,
+        SLA_r(Register),
+        SRA_HL,
+        SRA_r(Register),
+        SRL_HL,
+        SRL_r(Register),
+        SWAP_HL,
+        SWAP_r(Register),
+    }
+
+    impl CbInstruction {
+        pub fn from_byte(byte: u8) -> CbInstruction {
+            use self::CbInstruction::*;
+
+            let bit = (byte >> 3) & 0x07;
+            let operand = register_for_bits(byte);
+
+            match (byte >> 6, operand) {
+                (0, _) => match (bit, operand) {
+                    (0, Some(r)) => RLC_r(r),
+                    (0, None)    => RLC_HL,
+                    (1, Some(r)) => RRC_r(r),
+                    (1, None)    => RRC_HL,
+                    (2, Some(r)) => RL_r(r),
+                    (2, None)    => RL_HL,
+                    (3, Some(r)) => RR_r(r),
+                    (3, None)    => RR_HL,
+                    (4, Some(r)) => SLA_r(r),
+                    (4, None)    => SLA_HL,
+                    (5, Some(r)) => SRA_r(r),
+                    (5, None)    => SRA_HL,
+                    (6, Some(r)) => SWAP_r(r),
+                    (6, None)    => SWAP_HL,
+                    (_, Some(r)) => SRL_r(r),
+                    (_, None)    => SRL_HL,
+                },
+                (1, Some(r)) => BIT_b_r(bit, r),
+                (1, None)    => BIT_b_HL(bit),
+                (2, Some(r)) => RES_b_r(bit, r),
+                (2, None)    => RES_b_HL(bit),
+                (_, Some(r)) => SET_b_r(bit, r),
+                (_, None)    => SET_b_HL(bit),
+            }
+        }
+
+        // Machine cycles taken after the 0xCB prefix has been fetched.
+        pub fn cycles(self) -> u32 {
+            use self::CbInstruction::*;
+
+            match self {
+                BIT_b_HL(_) => 2,
+                RES_b_HL(_) | RL_HL | RLC_HL | RR_HL | RRC_HL | SET_b_HL(_) |
+                SLA_HL | SRA_HL | SRL_HL | SWAP_HL => 3,
+                _ => 1,
+            }
+        }
+    }
+
+    // Opcodes encode registers in three bits as B, C, D, E, H, L, (HL), A.
+    // The (HL) slot has no register and is returned as None.
+    fn register_for_bits(bits: u8) -> Option<Register> {
+        use registers::Register::*;
+
+        match bits & 0x07 {
+            0 => Some(B),
+            1 => Some(C),
+            2 => Some(D),
+            3 => Some(E),
+            4 => Some(H),
+            5 => Some(L),
+            6 => None,
+            _ => Some(A),
+        }
     }
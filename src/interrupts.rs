@@ -0,0 +1,82 @@
+// http://gbdev.gg8.se/wiki/articles/Interrupts
+use self::Interrupt::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interrupt { VBlank, LcdStat, Timer, Serial, Joypad }
+
+// In priority order, highest first.
+const INTERRUPTS: [Interrupt; 5] = [VBlank, LcdStat, Timer, Serial, Joypad];
+
+impl Interrupt {
+    pub fn mask(self) -> u8 {
+        match self {
+            VBlank  => 0x01,
+            LcdStat => 0x02,
+            Timer   => 0x04,
+            Serial  => 0x08,
+            Joypad  => 0x10,
+        }
+    }
+
+    pub fn vector(self) -> u16 {
+        match self {
+            VBlank  => 0x0040,
+            LcdStat => 0x0048,
+            Timer   => 0x0050,
+            Serial  => 0x0058,
+            Joypad  => 0x0060,
+        }
+    }
+}
+
+// The IE (0xFFFF) and IF (0xFF0F) registers.
+pub struct Interrupts {
+    enable: u8,
+    flags: u8,
+}
+
+impl Interrupts {
+    pub fn new() -> Interrupts {
+        Interrupts {
+            enable: 0,
+            flags: 0,
+        }
+    }
+
+    pub fn read_enable(&self) -> u8 {
+        self.enable
+    }
+
+    pub fn write_enable(&mut self, value: u8) {
+        self.enable = value;
+    }
+
+    // Only five bits of IF exist; the rest read as ones.
+    pub fn read_flags(&self) -> u8 {
+        self.flags | 0xE0
+    }
+
+    pub fn write_flags(&mut self, value: u8) {
+        self.flags = value & 0x1F;
+    }
+
+    pub fn request(&mut self, interrupt: Interrupt) {
+        self.flags |= interrupt.mask();
+    }
+
+    pub fn acknowledge(&mut self, interrupt: Interrupt) {
+        self.flags &= !interrupt.mask();
+    }
+
+    // The highest priority interrupt that is both requested and enabled.
+    pub fn pending(&self) -> Option<Interrupt> {
+        let active = self.enable & self.flags;
+        INTERRUPTS.iter().cloned().find(|i| active & i.mask() != 0)
+    }
+}
+
+impl Default for Interrupts {
+    fn default() -> Interrupts {
+        Interrupts::new()
+    }
+}
@@ -0,0 +1,127 @@
+// http://gbdev.gg8.se/wiki/articles/Joypad_Input
+use interrupts::{Interrupt, Interrupts};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Button { Right, Left, Up, Down, A, B, Select, Start }
+
+impl Button {
+    // The P1 bit a button pulls low, and the select line (bit 4 for the
+    // directions, bit 5 for the buttons) that has to be low to see it.
+    fn bits(self) -> (u8, u8) {
+        match self {
+            Button::Right  => (0x01, 0x10),
+            Button::Left   => (0x02, 0x10),
+            Button::Up     => (0x04, 0x10),
+            Button::Down   => (0x08, 0x10),
+            Button::A      => (0x01, 0x20),
+            Button::B      => (0x02, 0x20),
+            Button::Select => (0x04, 0x20),
+            Button::Start  => (0x08, 0x20),
+        }
+    }
+}
+
+// P1/JOYP: bits 4 and 5 select which group of buttons drives bits 0-3. All
+// of them are active low, and the joypad interrupt fires when any of the
+// input lines goes from high to low.
+pub struct Joypad {
+    select: u8,
+    directions: u8,
+    buttons: u8,
+}
+
+impl Joypad {
+    pub fn new() -> Joypad {
+        Joypad {
+            select: 0x30,
+            directions: 0,
+            buttons: 0,
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        0xC0 | self.select | self.lines()
+    }
+
+    pub fn write(&mut self, value: u8, interrupts: &mut Interrupts) {
+        let old_lines = self.lines();
+        self.select = value & 0x30;
+        self.detect_falling_edge(old_lines, interrupts);
+    }
+
+    pub fn set_button(&mut self, button: Button, pressed: bool, interrupts: &mut Interrupts) {
+        let old_lines = self.lines();
+
+        let (bit, select) = button.bits();
+        let group = if select == 0x10 { &mut self.directions } else { &mut self.buttons };
+        if pressed { *group |= bit } else { *group &= !bit }
+
+        self.detect_falling_edge(old_lines, interrupts);
+    }
+
+    // -------------------------------------------------------------------------
+
+    fn detect_falling_edge(&self, old_lines: u8, interrupts: &mut Interrupts) {
+        if old_lines & !self.lines() != 0 {
+            interrupts.request(Interrupt::Joypad);
+        }
+    }
+
+    // The state of bits 0-3, with a line pulled low by a pressed button in
+    // any selected group.
+    fn lines(&self) -> u8 {
+        let mut pressed = 0;
+        if self.select & 0x10 == 0 { pressed |= self.directions }
+        if self.select & 0x20 == 0 { pressed |= self.buttons }
+        !pressed & 0x0F
+    }
+}
+
+impl Default for Joypad {
+    fn default() -> Joypad {
+        Joypad::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_pressed_buttons_of_the_selected_group_low() {
+        let mut joypad = Joypad::new();
+        let mut interrupts = Interrupts::new();
+
+        joypad.set_button(Button::Down, true, &mut interrupts);
+        joypad.set_button(Button::A, true, &mut interrupts);
+
+        joypad.write(0x20, &mut interrupts);
+        assert_eq!(joypad.read(), 0xE7);
+        joypad.write(0x10, &mut interrupts);
+        assert_eq!(joypad.read(), 0xDE);
+        joypad.write(0x30, &mut interrupts);
+        assert_eq!(joypad.read(), 0xFF);
+    }
+
+    #[test]
+    fn interrupt_fires_only_on_high_to_low_edge() {
+        let mut joypad = Joypad::new();
+        let mut interrupts = Interrupts::new();
+        joypad.write(0x20, &mut interrupts);
+
+        // A button in the unselected group doesn't reach the lines.
+        joypad.set_button(Button::Start, true, &mut interrupts);
+        assert_eq!(interrupts.read_flags() & 0x10, 0);
+
+        joypad.set_button(Button::Up, true, &mut interrupts);
+        assert_eq!(interrupts.read_flags() & 0x10, 0x10);
+
+        interrupts.write_flags(0);
+        joypad.set_button(Button::Up, false, &mut interrupts);
+        assert_eq!(interrupts.read_flags() & 0x10, 0);
+
+        // Selecting the buttons lets the held Start pull its line low.
+        joypad.write(0x10, &mut interrupts);
+        assert_eq!(interrupts.read_flags() & 0x10, 0x10);
+    }
+}
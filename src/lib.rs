@@ -0,0 +1,17 @@
+pub mod apu;
+pub mod boot;
+pub mod bus;
+pub mod cartridge;
+pub mod cpu;
+pub mod disassembler;
+pub mod flags;
+pub mod instructions;
+pub mod interrupts;
+pub mod joypad;
+pub mod mappers;
+pub mod ppu;
+pub mod registers;
+pub mod save;
+pub mod serial;
+pub mod timer;
+pub mod wav;
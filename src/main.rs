@@ -1,15 +1,25 @@
-mod cpu;
-mod disassembler;
-mod flags;
-mod instructions;
-mod registers;
-
-use cpu::Cpu;
-use std::fs::File;
+extern crate rusty_boy;
+
+use rusty_boy::apu::CLOCK_RATE;
+use rusty_boy::boot::Model;
+use rusty_boy::cartridge::{CartridgeHeader, CgbSupport};
+use rusty_boy::cpu::{Cpu, StopReason};
+use rusty_boy::mappers;
+use rusty_boy::ppu::DOTS_PER_FRAME;
+use rusty_boy::save::SaveFile;
+use rusty_boy::serial::{self, CaptureLink};
+use rusty_boy::wav;
+use std::env;
+use std::fs::{self, File};
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const SAMPLE_RATE: u32 = 44_100;
 
 fn main() {
+    let options = parse_options();
+
     let path = Path::new("../gameboy/blargg-gb-tests/cpu_instrs/cpu_instrs.gb");
     let mut file = File::open(path)
         .expect("Unable to open file.");
@@ -18,9 +28,166 @@ fn main() {
     file.read_to_end(&mut memory)
         .expect("Unable to read file.");
 
+    let header = CartridgeHeader::parse(&memory).unwrap_or_else(|error| {
+        eprintln!("Unable to load cartridge: {}", error);
+        process::exit(1);
+    });
+    println!("{}", header);
+
+    let cartridge = mappers::new_mapper(&header, memory.clone());
+
+    let model = options.model.unwrap_or(
+        if header.cgb_support == CgbSupport::None { Model::Dmg } else { Model::Cgb });
+
     let mut cpu = Cpu::new();
-    cpu.load_memory(memory);
-    cpu.run();
+    cpu.load_cartridge(cartridge);
+
+    match options.boot_rom {
+        Some(boot_rom_path) => {
+            let boot_rom = fs::read(&boot_rom_path).unwrap_or_else(|error| {
+                eprintln!("Unable to read boot ROM {}: {}", boot_rom_path.display(), error);
+                process::exit(1);
+            });
+            cpu.load_boot_rom(model, boot_rom);
+        },
+        None => cpu.skip_boot_rom(model),
+    }
+    println!("Model:\t\t{:?}", model);
+
+    // Test ROMs print their results over the serial port.
+    let serial = CaptureLink::new();
+    let serial_output = serial.output();
+    cpu.set_serial_link(Box::new(serial));
+
+    if header.cartridge_type.battery {
+        let save_file = SaveFile::for_rom(path);
+        println!("Save file:\t{}", save_file.path().display());
+        cpu.attach_save_file(save_file)
+            .expect("Unable to read save file.");
+    }
+
+    if let Some((wav_path, frames)) = options.audio {
+        record_audio(&mut cpu, &wav_path, frames);
+    } else if let Some(frames) = options.link {
+        let mut peer = Cpu::new();
+        peer.load_cartridge(mappers::new_mapper(&header, memory));
+        peer.skip_boot_rom(model);
+        run_linked(&mut cpu, &mut peer, frames);
+    } else {
+        match cpu.run() {
+            StopReason::Halted =>
+                println!("Stopped: CPU halted"),
+            StopReason::InfiniteLoop(address) =>
+                println!("Stopped: infinite loop at {:#06X}", address),
+            StopReason::Stopped =>
+                println!("Stopped: CPU stopped waiting for input"),
+        }
+
+        println!("Serial output:\n{}", serial_output.borrow());
+    }
+
+    cpu.flush_save()
+        .expect("Unable to write save file.");
+}
+
+struct Options {
+    audio: Option<(PathBuf, u64)>,
+    boot_rom: Option<PathBuf>,
+    link: Option<u64>,
+    model: Option<Model>,
+}
+
+// --wav <file> <frames> runs headless for that many frames and writes the
+// audio to <file>, along with one <file>.chN.wav per APU channel.
+// --boot-rom <file> runs a boot ROM first instead of skipping to the state
+// it leaves behind. --link <frames> runs a second copy of the cartridge
+// joined to the first by a link cable for that many frames. --model <name> picks DMG0, DMG, MGB, SGB, CGB or AGB
+// rather than going by the cartridge header.
+fn parse_options() -> Options {
+    let mut options = Options { audio: None, boot_rom: None, link: None, model: None };
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--wav", Some(file)) => {
+                let frames = args.next().and_then(|frames| frames.parse::<u64>().ok())
+                    .unwrap_or_else(|| {
+                        eprintln!("--wav needs a file and a frame count");
+                        process::exit(1);
+                    });
+                options.audio = Some((PathBuf::from(file), frames));
+            },
+            ("--boot-rom", Some(file)) => options.boot_rom = Some(PathBuf::from(file)),
+            ("--link", Some(frames)) => {
+                options.link = Some(frames.parse::<u64>().unwrap_or_else(|_| {
+                    eprintln!("--link needs a frame count");
+                    process::exit(1);
+                }));
+            },
+            ("--model", Some(name)) => {
+                options.model = Some(Model::from_name(&name).unwrap_or_else(|| {
+                    eprintln!("Unknown model: {}", name);
+                    process::exit(1);
+                }));
+            },
+            _ => {
+                eprintln!("Unrecognised argument: {}", arg);
+                process::exit(1);
+            },
+        }
+    }
+
+    options
+}
+
+// Steps whichever core is behind so that the two stay within an
+// instruction of each other, which is as close as the cable needs them.
+fn run_linked(cpu: &mut Cpu, peer: &mut Cpu, frames: u64) {
+    cpu.set_logging(false);
+    peer.set_logging(false);
+
+    let (first, second) = serial::link_cable();
+    cpu.set_serial_link(Box::new(first));
+    peer.set_serial_link(Box::new(second));
+
+    let end = cpu.cycles() + frames * DOTS_PER_FRAME as u64 / 4;
+    while cpu.cycles() < end {
+        if cpu.cycles() <= peer.cycles() { cpu.step(); } else { peer.step(); }
+    }
+    println!("Linked:\t\t{} frames", frames);
+}
+
+fn record_audio(cpu: &mut Cpu, path: &Path, frames: u64) {
+    cpu.set_logging(false);
+    cpu.set_sample_rate(SAMPLE_RATE);
+    cpu.set_channel_capture(true);
+
+    let length = (frames * DOTS_PER_FRAME as u64 * SAMPLE_RATE as u64
+        / CLOCK_RATE as u64) as usize;
+
+    let mut samples = Vec::new();
+    let mut channels = [Vec::new(), Vec::new(), Vec::new(), Vec::new()];
+
+    while samples.len() < length * 2 {
+        cpu.step();
+        samples.extend(cpu.take_audio_samples());
+        for (buffer, new_samples) in channels.iter_mut().zip(cpu.take_channel_samples().iter()) {
+            buffer.extend_from_slice(new_samples);
+        }
+    }
+
+    samples.truncate(length * 2);
+    wav::save(path, SAMPLE_RATE, 2, &samples)
+        .expect("Unable to write WAV file.");
+    println!("Audio:\t\t{}", path.display());
+
+    for (channel, buffer) in channels.iter_mut().enumerate() {
+        buffer.truncate(length);
+        let channel_path = path.with_extension(format!("ch{}.wav", channel + 1));
+        wav::save(&channel_path, SAMPLE_RATE, 1, buffer)
+            .expect("Unable to write WAV file.");
+        println!("Channel {}:\t{}", channel + 1, channel_path.display());
+    }
 }
 
 // http://www.pastraiser.com/cpu/gameboy/gameboy_opcodes.html
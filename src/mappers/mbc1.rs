@@ -0,0 +1,164 @@
+use super::{load_ram, read_banked, Mapper, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+pub struct Mbc1 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    bank1: u8,
+    bank2: u8,
+    advanced_banking: bool,
+}
+
+impl Mbc1 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Mbc1 {
+        Mbc1 {
+            rom,
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            bank1: 1,
+            bank2: 0,
+            advanced_banking: false,
+        }
+    }
+
+    // In advanced mode BANK2 also applies to 0x0000-0x3FFF and to RAM.
+    fn zero_bank(&self) -> usize {
+        if self.advanced_banking { (self.bank2 as usize) << 5 } else { 0 }
+    }
+
+    fn ram_bank(&self) -> usize {
+        if self.advanced_banking { self.bank2 as usize } else { 0 }
+    }
+
+    fn ram_offset(&self, address: u16) -> usize {
+        self.ram_bank() * RAM_BANK_SIZE + (address as usize - 0xA000)
+    }
+}
+
+impl Mapper for Mbc1 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let address = address as usize;
+
+        match address {
+            0x0000..=0x3FFF => {
+                read_banked(&self.rom, self.zero_bank(), ROM_BANK_SIZE, address)
+            },
+            _ => {
+                let bank = ((self.bank2 as usize) << 5) | self.bank1 as usize;
+                read_banked(&self.rom, bank, ROM_BANK_SIZE, address - 0x4000)
+            },
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+
+            // Only the five bits written here are checked for zero, which is
+            // why banks 0x20, 0x40 and 0x60 can never be selected.
+            0x2000..=0x3FFF => {
+                self.bank1 = value & 0x1F;
+                if self.bank1 == 0 { self.bank1 = 1 }
+            },
+
+            0x4000..=0x5FFF => self.bank2 = value & 0x03,
+            _ => self.advanced_banking = value & 0x01 == 0x01,
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() { return 0xFF }
+
+        let offset = self.ram_offset(address) % self.ram.len();
+        self.ram[offset]
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) -> bool {
+        if !self.ram_enabled || self.ram.is_empty() { return false }
+
+        let offset = self.ram_offset(address) % self.ram.len();
+        self.ram[offset] = value;
+        true
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.to_vec()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 2MiB ROM whose banks each start with their own number.
+    fn mbc1(ram_size: usize) -> Mbc1 {
+        let mut rom = vec![0; 128 * ROM_BANK_SIZE];
+        for bank in 0..128 {
+            rom[bank * ROM_BANK_SIZE] = bank as u8;
+        }
+        Mbc1::new(rom, ram_size)
+    }
+
+    #[test]
+    fn bank_zero_selects_bank_one() {
+        let mut mbc1 = mbc1(0);
+        assert_eq!(mbc1.read_rom(0x4000), 1);
+
+        mbc1.write_rom(0x2000, 0x00);
+        assert_eq!(mbc1.read_rom(0x4000), 1);
+
+        mbc1.write_rom(0x2000, 0x05);
+        assert_eq!(mbc1.read_rom(0x4000), 5);
+    }
+
+    #[test]
+    fn bank_0x20_selects_0x21() {
+        let mut mbc1 = mbc1(0);
+        mbc1.write_rom(0x4000, 0x01);
+        mbc1.write_rom(0x2000, 0x00);
+        assert_eq!(mbc1.read_rom(0x4000), 0x21);
+
+        mbc1.write_rom(0x4000, 0x03);
+        mbc1.write_rom(0x2000, 0x02);
+        assert_eq!(mbc1.read_rom(0x4000), 0x62);
+    }
+
+    #[test]
+    fn mode_1_applies_upper_bits_to_bank_zero_area() {
+        let mut mbc1 = mbc1(0);
+        mbc1.write_rom(0x4000, 0x02);
+        assert_eq!(mbc1.read_rom(0x0000), 0x00);
+
+        mbc1.write_rom(0x6000, 0x01);
+        assert_eq!(mbc1.read_rom(0x0000), 0x40);
+
+        mbc1.write_rom(0x6000, 0x00);
+        assert_eq!(mbc1.read_rom(0x0000), 0x00);
+    }
+
+    #[test]
+    fn ram_needs_enabling_and_banks_in_mode_1() {
+        let mut mbc1 = mbc1(4 * RAM_BANK_SIZE);
+        mbc1.write_ram(0xA000, 0x11);
+        assert_eq!(mbc1.read_ram(0xA000), 0xFF);
+
+        mbc1.write_rom(0x0000, 0x0A);
+        mbc1.write_ram(0xA000, 0x11);
+        mbc1.write_rom(0x4000, 0x02);
+        assert_eq!(mbc1.read_ram(0xA000), 0x11);
+
+        mbc1.write_rom(0x6000, 0x01);
+        mbc1.write_ram(0xA000, 0x22);
+        assert_eq!(mbc1.read_ram(0xA000), 0x22);
+
+        mbc1.write_rom(0x4000, 0x00);
+        assert_eq!(mbc1.read_ram(0xA000), 0x11);
+
+        mbc1.write_rom(0x0000, 0x00);
+        assert_eq!(mbc1.read_ram(0xA000), 0xFF);
+    }
+}
@@ -0,0 +1,105 @@
+use super::{load_ram, read_banked, Mapper, ROM_BANK_SIZE};
+
+const RAM_SIZE: usize = 0x200;
+
+pub struct Mbc2 {
+    rom: Vec<u8>,
+    ram: [u8; RAM_SIZE],
+    ram_enabled: bool,
+    rom_bank: u8,
+}
+
+impl Mbc2 {
+    pub fn new(rom: Vec<u8>) -> Mbc2 {
+        Mbc2 {
+            rom,
+            ram: [0; RAM_SIZE],
+            ram_enabled: false,
+            rom_bank: 1,
+        }
+    }
+}
+
+impl Mapper for Mbc2 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let address = address as usize;
+
+        match address {
+            0x0000..=0x3FFF => read_banked(&self.rom, 0, ROM_BANK_SIZE, address),
+            _ => read_banked(&self.rom, self.rom_bank as usize, ROM_BANK_SIZE, address - 0x4000),
+        }
+    }
+
+    // Both registers live in 0x0000-0x3FFF; bit 8 of the address picks
+    // between RAM enable and ROM bank.
+    fn write_rom(&mut self, address: u16, value: u8) {
+        if address > 0x3FFF { return }
+
+        if address & 0x0100 == 0 {
+            self.ram_enabled = value & 0x0F == 0x0A;
+        } else {
+            self.rom_bank = value & 0x0F;
+            if self.rom_bank == 0 { self.rom_bank = 1 }
+        }
+    }
+
+    // The built-in RAM is 512 half-bytes, repeated across 0xA000-0xBFFF. The
+    // upper four bits are not connected and read back as ones.
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled { return 0xFF }
+
+        self.ram[address as usize % RAM_SIZE] | 0xF0
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) -> bool {
+        if !self.ram_enabled { return false }
+
+        self.ram[address as usize % RAM_SIZE] = value & 0x0F;
+        true
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.to_vec()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn address_bit_8_selects_register() {
+        let mut rom = vec![0; 16 * ROM_BANK_SIZE];
+        rom[3 * ROM_BANK_SIZE] = 3;
+        let mut mbc2 = Mbc2::new(rom);
+
+        mbc2.write_rom(0x2000, 0x03);
+        assert_eq!(mbc2.read_rom(0x4000), 0);
+
+        mbc2.write_rom(0x2100, 0x03);
+        assert_eq!(mbc2.read_rom(0x4000), 3);
+
+        mbc2.write_rom(0x0100, 0x0A);
+        assert_eq!(mbc2.read_ram(0xA000), 0xFF);
+        mbc2.write_rom(0x0000, 0x0A);
+        assert_eq!(mbc2.read_ram(0xA000), 0xF0);
+    }
+
+    #[test]
+    fn ram_holds_nibbles_mirrored_every_512_bytes() {
+        let mut mbc2 = Mbc2::new(vec![0; 2 * ROM_BANK_SIZE]);
+        mbc2.write_rom(0x0000, 0x0A);
+
+        mbc2.write_ram(0xA001, 0xAB);
+        assert_eq!(mbc2.read_ram(0xA001), 0xFB);
+        assert_eq!(mbc2.read_ram(0xA201), 0xFB);
+        assert_eq!(mbc2.read_ram(0xBE01), 0xFB);
+
+        mbc2.write_ram(0xBFFF, 0x05);
+        assert_eq!(mbc2.read_ram(0xA1FF), 0xF5);
+    }
+}
@@ -0,0 +1,116 @@
+use super::rtc::Rtc;
+use super::{load_ram, read_banked, Mapper, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+pub struct Mbc3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    rtc: Option<Rtc>,
+    ram_enabled: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+    latch_armed: bool,
+}
+
+impl Mbc3 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, rtc: Option<Rtc>) -> Mbc3 {
+        Mbc3 {
+            rom,
+            ram: vec![0; ram_size],
+            rtc,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            latch_armed: false,
+        }
+    }
+
+    fn ram_offset(&self, address: u16) -> Option<usize> {
+        if self.ram.is_empty() { return None }
+
+        let offset = self.ram_bank as usize * RAM_BANK_SIZE + (address as usize - 0xA000);
+        Some(offset % self.ram.len())
+    }
+}
+
+impl Mapper for Mbc3 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let address = address as usize;
+
+        match address {
+            0x0000..=0x3FFF => read_banked(&self.rom, 0, ROM_BANK_SIZE, address),
+            _ => read_banked(&self.rom, self.rom_bank as usize, ROM_BANK_SIZE, address - 0x4000),
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+
+            0x2000..=0x3FFF => {
+                self.rom_bank = value & 0x7F;
+                if self.rom_bank == 0 { self.rom_bank = 1 }
+            },
+
+            0x4000..=0x5FFF => self.ram_bank = value,
+
+            // Writing 0x00 then 0x01 copies the live clock into the
+            // registers the CPU reads.
+            _ => {
+                if self.latch_armed && value == 0x01 {
+                    if let Some(ref mut rtc) = self.rtc { rtc.latch() }
+                }
+                self.latch_armed = value == 0x00;
+            },
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled { return 0xFF }
+
+        match (self.ram_bank, &self.rtc) {
+            (0x00..=0x03, _) => match self.ram_offset(address) {
+                Some(offset) => self.ram[offset],
+                None => 0xFF,
+            },
+            (0x08..=0x0C, Some(rtc)) => rtc.read(self.ram_bank),
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) -> bool {
+        if !self.ram_enabled { return false }
+
+        match self.ram_bank {
+            0x00..=0x03 => match self.ram_offset(address) {
+                Some(offset) => {
+                    self.ram[offset] = value;
+                    true
+                },
+                None => false,
+            },
+            0x08..=0x0C => {
+                if let Some(ref mut rtc) = self.rtc { rtc.write(self.ram_bank, value) }
+                false
+            },
+            _ => false,
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        if let Some(ref rtc) = self.rtc {
+            data.extend(rtc.save());
+        }
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+
+        if let Some(ref mut rtc) = self.rtc {
+            if data.len() > self.ram.len() {
+                rtc.load(&data[self.ram.len()..]);
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use super::{load_ram, read_banked, Mapper, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+pub struct Mbc5 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    has_rumble: bool,
+    ram_enabled: bool,
+    rom_bank: u16,
+    ram_bank: u8,
+    rumble: bool,
+}
+
+impl Mbc5 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, has_rumble: bool) -> Mbc5 {
+        Mbc5 {
+            rom,
+            ram: vec![0; ram_size],
+            has_rumble,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            rumble: false,
+        }
+    }
+
+    fn ram_offset(&self, address: u16) -> Option<usize> {
+        if !self.ram_enabled || self.ram.is_empty() { return None }
+
+        let offset = self.ram_bank as usize * RAM_BANK_SIZE + (address as usize - 0xA000);
+        Some(offset % self.ram.len())
+    }
+}
+
+impl Mapper for Mbc5 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let address = address as usize;
+
+        match address {
+            0x0000..=0x3FFF => read_banked(&self.rom, 0, ROM_BANK_SIZE, address),
+            _ => read_banked(&self.rom, self.rom_bank as usize, ROM_BANK_SIZE, address - 0x4000),
+        }
+    }
+
+    // Unlike MBC1 and MBC3, bank 0 can be mapped into 0x4000-0x7FFF.
+    fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | value as u16,
+            0x3000..=0x3FFF => {
+                self.rom_bank = (self.rom_bank & 0xFF) | ((value as u16 & 0x01) << 8);
+            },
+
+            // On rumble cartridges bit 3 drives the motor instead of
+            // selecting a RAM bank.
+            0x4000..=0x5FFF => {
+                if self.has_rumble {
+                    self.rumble = value & 0x08 == 0x08;
+                    self.ram_bank = value & 0x07;
+                } else {
+                    self.ram_bank = value & 0x0F;
+                }
+            },
+
+            _ => (),
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match self.ram_offset(address) {
+            Some(offset) => self.ram[offset],
+            None => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) -> bool {
+        match self.ram_offset(address) {
+            Some(offset) => {
+                self.ram[offset] = value;
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn rumble(&self) -> bool {
+        self.rumble
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.to_vec()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An 8MiB ROM whose banks each start with their own number.
+    fn mbc5(ram_size: usize, has_rumble: bool) -> Mbc5 {
+        let mut rom = vec![0; 512 * ROM_BANK_SIZE];
+        for bank in 0..512 {
+            rom[bank * ROM_BANK_SIZE] = bank as u8;
+            rom[bank * ROM_BANK_SIZE + 1] = (bank >> 8) as u8;
+        }
+        Mbc5::new(rom, ram_size, has_rumble)
+    }
+
+    fn bank(mbc5: &Mbc5) -> u16 {
+        mbc5.read_rom(0x4000) as u16 | (mbc5.read_rom(0x4001) as u16) << 8
+    }
+
+    #[test]
+    fn rom_bank_is_nine_bits() {
+        let mut mbc5 = mbc5(0, false);
+        mbc5.write_rom(0x2000, 0x23);
+        mbc5.write_rom(0x3000, 0x01);
+        assert_eq!(bank(&mbc5), 0x123);
+
+        mbc5.write_rom(0x2000, 0xFF);
+        assert_eq!(bank(&mbc5), 0x1FF);
+
+        mbc5.write_rom(0x3000, 0x00);
+        assert_eq!(bank(&mbc5), 0x0FF);
+    }
+
+    #[test]
+    fn bank_zero_can_be_mapped_high() {
+        let mut mbc5 = mbc5(0, false);
+        mbc5.write_rom(0x2000, 0x00);
+        assert_eq!(bank(&mbc5), 0);
+    }
+
+    #[test]
+    fn rumble_bit_drives_motor_not_ram_bank() {
+        let mut mbc5 = mbc5(4 * RAM_BANK_SIZE, true);
+        mbc5.write_rom(0x0000, 0x0A);
+        mbc5.write_ram(0xA000, 0x11);
+
+        mbc5.write_rom(0x4000, 0x08);
+        assert!(mbc5.rumble());
+        assert_eq!(mbc5.read_ram(0xA000), 0x11);
+
+        mbc5.write_rom(0x4000, 0x01);
+        assert!(!mbc5.rumble());
+        assert_eq!(mbc5.read_ram(0xA000), 0x00);
+    }
+}
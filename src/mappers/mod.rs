@@ -0,0 +1,80 @@
+// http://gbdev.gg8.se/wiki/articles/Memory_Bank_Controllers
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+mod rom_only;
+pub mod rtc;
+
+pub use self::mbc1::Mbc1;
+pub use self::mbc2::Mbc2;
+pub use self::mbc3::Mbc3;
+pub use self::mbc5::Mbc5;
+pub use self::rom_only::RomOnly;
+
+use self::rtc::{Clock, Rtc, SystemClock};
+use cartridge::{CartridgeHeader, MapperType};
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+// A mapper sits between the bus and the cartridge, translating accesses to
+// 0x0000-0x7FFF and 0xA000-0xBFFF into ROM and RAM offsets. Writes to the ROM
+// area program the mapper's registers.
+pub trait Mapper {
+    fn read_rom(&self, address: u16) -> u8;
+    fn write_rom(&mut self, address: u16, value: u8);
+    fn read_ram(&self, address: u16) -> u8;
+
+    // Returns whether the write reached cartridge RAM, and so whether the
+    // save file is out of date.
+    fn write_ram(&mut self, address: u16, value: u8) -> bool;
+
+    // Whether a rumble motor is currently switched on.
+    fn rumble(&self) -> bool { false }
+
+    // Cartridge RAM, followed by any clock state, as laid out in a .sav file.
+    fn save_data(&self) -> Vec<u8>;
+    fn load_save_data(&mut self, data: &[u8]);
+}
+
+// Header parsing has already rejected cartridge types without a mapper.
+pub fn new_mapper(header: &CartridgeHeader, rom: Vec<u8>) -> Box<dyn Mapper> {
+    new_mapper_with_clock(header, rom, Box::new(SystemClock))
+}
+
+// The same, with the clock an MBC3's RTC counts time by.
+pub fn new_mapper_with_clock(header: &CartridgeHeader,
+                             rom: Vec<u8>,
+                             clock: Box<dyn Clock>) -> Box<dyn Mapper> {
+    let cartridge_type = header.cartridge_type;
+    let ram_size = header.ram_size;
+
+    match cartridge_type.mapper {
+        MapperType::RomOnly => Box::new(RomOnly::new(rom, ram_size)),
+        MapperType::Mbc1 => Box::new(Mbc1::new(rom, ram_size)),
+        MapperType::Mbc2 => Box::new(Mbc2::new(rom)),
+        MapperType::Mbc3 => {
+            let rtc = if cartridge_type.timer {
+                Some(Rtc::new(clock))
+            } else {
+                None
+            };
+            Box::new(Mbc3::new(rom, ram_size, rtc))
+        },
+        MapperType::Mbc5 => Box::new(Mbc5::new(rom, ram_size, cartridge_type.rumble)),
+    }
+}
+
+fn load_ram(ram: &mut [u8], data: &[u8]) {
+    let length = ram.len().min(data.len());
+    ram[..length].copy_from_slice(&data[..length]);
+}
+
+fn read_banked(memory: &[u8], bank: usize, bank_size: usize, offset: usize) -> u8 {
+    if memory.is_empty() { return 0xFF }
+
+    let banks = (memory.len() / bank_size).max(1);
+    let address = (bank % banks) * bank_size + offset;
+    *memory.get(address).unwrap_or(&0xFF)
+}
@@ -0,0 +1,69 @@
+use super::{load_ram, Mapper};
+
+// Up to 32 KiB of ROM mapped flat, optionally with a single 8 KiB RAM chip
+// that is always enabled.
+pub struct RomOnly {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+}
+
+impl RomOnly {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> RomOnly {
+        RomOnly {
+            rom,
+            ram: vec![0; ram_size],
+        }
+    }
+}
+
+impl Mapper for RomOnly {
+    fn read_rom(&self, address: u16) -> u8 {
+        *self.rom.get(address as usize).unwrap_or(&0xFF)
+    }
+
+    fn write_rom(&mut self, _address: u16, _value: u8) {}
+
+    fn read_ram(&self, address: u16) -> u8 {
+        *self.ram.get(address as usize - 0xA000).unwrap_or(&0xFF)
+    }
+
+    fn write_ram(&mut self, address: u16, value: u8) -> bool {
+        match self.ram.get_mut(address as usize - 0xA000) {
+            Some(byte) => {
+                *byte = value;
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.to_vec()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rom_is_read_only_and_ram_always_enabled() {
+        let mut rom_only = RomOnly::new(vec![0x12; 0x8000], 0x2000);
+        rom_only.write_rom(0x2000, 0x34);
+        assert_eq!(rom_only.read_rom(0x2000), 0x12);
+
+        rom_only.write_ram(0xBFFF, 0x56);
+        assert_eq!(rom_only.read_ram(0xBFFF), 0x56);
+    }
+
+    #[test]
+    fn missing_ram_reads_ff() {
+        let mut rom_only = RomOnly::new(vec![0; 0x8000], 0);
+        rom_only.write_ram(0xA000, 0x56);
+        assert_eq!(rom_only.read_ram(0xA000), 0xFF);
+    }
+}
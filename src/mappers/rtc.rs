@@ -0,0 +1,324 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+const DAYS: u64 = 512;
+
+const SAVE_SIZE: usize = 48;
+
+// Seconds since the Unix epoch. The RTC only ever looks at the difference
+// between two readings, so tests can substitute a clock they advance by hand.
+pub trait Clock {
+    fn now(&self) -> u64;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+// http://gbdev.gg8.se/wiki/articles/MBC3#The_Clock_Counter_Registers
+pub struct Rtc {
+    clock: Box<dyn Clock>,
+    last_update: u64,
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    days: u16,
+    halted: bool,
+    day_carry: bool,
+    latched: [u8; 5],
+}
+
+impl Rtc {
+    pub fn new(clock: Box<dyn Clock>) -> Rtc {
+        let last_update = clock.now();
+
+        Rtc {
+            clock,
+            last_update,
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            days: 0,
+            halted: false,
+            day_carry: false,
+            latched: [0; 5],
+        }
+    }
+
+    pub fn latch(&mut self) {
+        self.update();
+        self.latched = self.registers();
+    }
+
+    // Registers 0x08-0x0C: seconds, minutes, hours, day low, day high.
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched[(register - 0x08) as usize]
+    }
+
+    pub fn write(&mut self, register: u8, value: u8) {
+        self.update();
+
+        match register {
+            0x08 => self.seconds = value & 0x3F,
+            0x09 => self.minutes = value & 0x3F,
+            0x0A => self.hours = value & 0x1F,
+            0x0B => self.days = (self.days & 0x100) | value as u16,
+            _ => {
+                self.days = (self.days & 0xFF) | ((value as u16 & 0x01) << 8);
+                self.halted = value & 0x40 == 0x40;
+                self.day_carry = value & 0x80 == 0x80;
+            },
+        }
+    }
+
+    // The layout shared by VBA-M, BGB and mGBA: the live and latched
+    // registers as little-endian u32s followed by a little-endian Unix
+    // timestamp. Older files store the timestamp in 32 bits.
+    pub fn save(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(SAVE_SIZE);
+
+        for &register in self.registers().iter().chain(self.latched.iter()) {
+            data.extend_from_slice(&(register as u32).to_le_bytes());
+        }
+        data.extend_from_slice(&self.last_update.to_le_bytes());
+        data
+    }
+
+    pub fn load(&mut self, data: &[u8]) {
+        if data.len() < SAVE_SIZE - 4 { return }
+
+        let mut registers = [0u8; 10];
+        for (i, register) in registers.iter_mut().enumerate() {
+            *register = data[i * 4];
+        }
+
+        let mut timestamp = [0u8; 8];
+        let length = (data.len() - 40).min(8);
+        timestamp[..length].copy_from_slice(&data[40..40 + length]);
+
+        self.seconds = registers[0] & 0x3F;
+        self.minutes = registers[1] & 0x3F;
+        self.hours = registers[2] & 0x1F;
+        self.days = ((registers[4] as u16 & 0x01) << 8) | registers[3] as u16;
+        self.halted = registers[4] & 0x40 == 0x40;
+        self.day_carry = registers[4] & 0x80 == 0x80;
+        self.latched.copy_from_slice(&registers[5..10]);
+        self.last_update = u64::from_le_bytes(timestamp);
+
+        self.update();
+    }
+
+    // -------------------------------------------------------------------------
+
+    fn registers(&self) -> [u8; 5] {
+        let mut day_high = (self.days >> 8) as u8;
+        if self.halted    { day_high |= 0x40 }
+        if self.day_carry { day_high |= 0x80 }
+
+        [self.seconds, self.minutes, self.hours, self.days as u8, day_high]
+    }
+
+    fn update(&mut self) {
+        let now = self.clock.now();
+        let elapsed = now.saturating_sub(self.last_update);
+        self.last_update = now;
+
+        if self.halted || elapsed == 0 { return }
+
+        let total = self.seconds as u64
+            + self.minutes as u64 * 60
+            + self.hours as u64 * 60 * 60
+            + self.days as u64 * SECONDS_PER_DAY
+            + elapsed;
+
+        let days = total / SECONDS_PER_DAY;
+        if days >= DAYS { self.day_carry = true }
+
+        self.seconds = (total % 60) as u8;
+        self.minutes = (total / 60 % 60) as u8;
+        self.hours = (total / (60 * 60) % 24) as u8;
+        self.days = (days % DAYS) as u16;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cartridge::CartridgeHeader;
+    use mappers::new_mapper_with_clock;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[derive(Clone)]
+    struct ManualClock(Rc<Cell<u64>>);
+
+    impl ManualClock {
+        fn advance(&self, seconds: u64) {
+            self.0.set(self.0.get() + seconds);
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> u64 {
+            self.0.get()
+        }
+    }
+
+    fn new_rtc() -> (Rtc, ManualClock) {
+        let clock = ManualClock(Rc::new(Cell::new(1_500_000_000)));
+        (Rtc::new(Box::new(clock.clone())), clock)
+    }
+
+    fn latched(rtc: &mut Rtc) -> [u8; 5] {
+        rtc.latch();
+        [rtc.read(0x08), rtc.read(0x09), rtc.read(0x0A), rtc.read(0x0B), rtc.read(0x0C)]
+    }
+
+    fn set_time(rtc: &mut Rtc, registers: [u8; 5]) {
+        for (register, &value) in (0x08..=0x0C).zip(registers.iter()) {
+            rtc.write(register, value);
+        }
+    }
+
+    // A 32KiB MBC3+TIMER+RAM+BATTERY cartridge with 8KiB of RAM.
+    fn mbc3_rom() -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0147] = 0x10;
+        rom[0x0149] = 0x02;
+        rom[0x014D] = rom[0x0134..0x014D]
+            .iter()
+            .fold(0u8, |sum, &b| sum.wrapping_sub(b).wrapping_sub(1));
+
+        let checksum = rom.iter().fold(0u16, |sum, &b| sum.wrapping_add(b as u16));
+        rom[0x014E] = (checksum >> 8) as u8;
+        rom[0x014F] = checksum as u8;
+        rom
+    }
+
+    #[test]
+    fn seconds_roll_over_into_minutes() {
+        let (mut rtc, clock) = new_rtc();
+        clock.advance(59);
+        assert_eq!(latched(&mut rtc), [59, 0, 0, 0, 0]);
+
+        clock.advance(1);
+        assert_eq!(latched(&mut rtc), [0, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn minutes_roll_over_into_hours() {
+        let (mut rtc, clock) = new_rtc();
+        set_time(&mut rtc, [59, 59, 0, 0, 0]);
+
+        clock.advance(1);
+        assert_eq!(latched(&mut rtc), [0, 0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn hours_roll_over_into_days() {
+        let (mut rtc, clock) = new_rtc();
+        set_time(&mut rtc, [59, 59, 23, 0, 0]);
+
+        clock.advance(1);
+        assert_eq!(latched(&mut rtc), [0, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn days_roll_over_into_day_high_bit() {
+        let (mut rtc, clock) = new_rtc();
+        set_time(&mut rtc, [59, 59, 23, 0xFF, 0]);
+
+        clock.advance(1);
+        assert_eq!(latched(&mut rtc), [0, 0, 0, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn day_carry_is_set_after_512_days_and_stays_set() {
+        let (mut rtc, clock) = new_rtc();
+        set_time(&mut rtc, [59, 59, 23, 0xFF, 0x01]);
+
+        clock.advance(1);
+        assert_eq!(latched(&mut rtc), [0, 0, 0, 0x00, 0x80]);
+
+        clock.advance(SECONDS_PER_DAY);
+        assert_eq!(latched(&mut rtc), [0, 0, 0, 0x01, 0x80]);
+
+        rtc.write(0x0C, 0x00);
+        assert_eq!(latched(&mut rtc), [0, 0, 0, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn halt_bit_stops_time() {
+        let (mut rtc, clock) = new_rtc();
+        set_time(&mut rtc, [10, 0, 0, 0, 0x40]);
+
+        clock.advance(100);
+        assert_eq!(latched(&mut rtc), [10, 0, 0, 0, 0x40]);
+
+        rtc.write(0x0C, 0x00);
+        clock.advance(5);
+        assert_eq!(latched(&mut rtc), [15, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn latch_needs_zero_then_one() {
+        let rom = mbc3_rom();
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        let clock = ManualClock(Rc::new(Cell::new(0)));
+        let mut mapper = new_mapper_with_clock(&header, rom, Box::new(clock.clone()));
+
+        mapper.write_rom(0x0000, 0x0A);
+        mapper.write_rom(0x4000, 0x08);
+
+        clock.advance(5);
+        mapper.write_rom(0x6000, 0x01);
+        assert_eq!(mapper.read_ram(0xA000), 0);
+
+        mapper.write_rom(0x6000, 0x00);
+        mapper.write_rom(0x6000, 0x01);
+        assert_eq!(mapper.read_ram(0xA000), 5);
+
+        // The latched value holds until the next latch.
+        clock.advance(5);
+        assert_eq!(mapper.read_ram(0xA000), 5);
+
+        mapper.write_rom(0x6000, 0x00);
+        mapper.write_rom(0x6000, 0x01);
+        assert_eq!(mapper.read_ram(0xA000), 10);
+    }
+
+    #[test]
+    fn save_round_trips_and_counts_time_spent_saved() {
+        let (mut rtc, clock) = new_rtc();
+        set_time(&mut rtc, [30, 20, 10, 0x34, 0x81]);
+        rtc.latch();
+        clock.advance(3);
+
+        let data = rtc.save();
+        assert_eq!(data.len(), SAVE_SIZE);
+
+        let (mut loaded, loaded_clock) = new_rtc();
+        loaded_clock.0.set(clock.0.get() + 60);
+        loaded.load(&data);
+
+        assert_eq!(loaded.latched, [30, 20, 10, 0x34, 0x81]);
+        assert_eq!(latched(&mut loaded), [33, 21, 10, 0x34, 0x81]);
+    }
+
+    #[test]
+    fn save_data_appends_clock_to_cartridge_ram() {
+        let rom = mbc3_rom();
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        let clock = ManualClock(Rc::new(Cell::new(0)));
+        let mapper = new_mapper_with_clock(&header, rom, Box::new(clock));
+
+        assert_eq!(mapper.save_data().len(), 0x2000 + SAVE_SIZE);
+    }
+}
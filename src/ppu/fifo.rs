@@ -0,0 +1,232 @@
+// https://gbdev.io/pandocs/pixel_fifo.html
+use super::{tile_color, Ppu, SCREEN_WIDTH};
+use std::collections::VecDeque;
+
+// A tile row takes six dots to fetch: two each for the tile number and the
+// two bitplanes. Sprite rows take the same time, during which nothing is
+// shifted out to the LCD.
+const FETCH_DOTS: u8 = 6;
+const SPRITE_FETCH_DOTS: u8 = 6;
+
+#[derive(Clone, Copy)]
+struct BackgroundPixel {
+    color: u8,
+    attributes: u8,
+}
+
+#[derive(Clone, Copy)]
+struct SpritePixel {
+    color: u8,
+    flags: u8,
+    index: u8,
+}
+
+const TRANSPARENT: SpritePixel = SpritePixel {
+    color: 0,
+    flags: 0,
+    index: 0,
+};
+
+pub struct PixelFifo {
+    background: VecDeque<BackgroundPixel>,
+    sprites: VecDeque<SpritePixel>,
+    fetch_dot: u8,
+    fetch_x: u8,
+    tile_row: u16,
+    attributes: u8,
+    low: u8,
+    high: u8,
+    first_fetch: bool,
+    discard: u8,
+    x: u8,
+    window: bool,
+    next_sprite: usize,
+    sprite_stall: u8,
+}
+
+impl PixelFifo {
+    pub fn new() -> PixelFifo {
+        PixelFifo {
+            background: VecDeque::with_capacity(16),
+            sprites: VecDeque::with_capacity(8),
+            fetch_dot: 0,
+            fetch_x: 0,
+            tile_row: 0,
+            attributes: 0,
+            low: 0,
+            high: 0,
+            first_fetch: true,
+            discard: 0,
+            x: 0,
+            window: false,
+            next_sprite: 0,
+            sprite_stall: 0,
+        }
+    }
+}
+
+// Mode 3 is 172 dots when nothing stalls the fetcher: the first tile of every
+// line is fetched twice before the 160 pixels are shifted out. SCX % 8 pixels
+// are then discarded, the window restarts the fetcher when reached, and each
+// sprite waits for the current background fetch before fetching its own row.
+impl Ppu {
+    pub(super) fn start_pixel_fifo(&mut self) {
+        self.fifo = PixelFifo::new();
+        self.fifo.discard = self.scx % 8;
+    }
+
+    // Runs one dot of mode 3, returning true once the last pixel of the line
+    // has been drawn.
+    pub(super) fn tick_pixel_fifo(&mut self) -> bool {
+        if self.fifo.sprite_stall == 0 && self.sprite_due() {
+            if !self.fetcher_idle() {
+                self.tick_fetcher();
+                return false;
+            }
+            self.fifo.sprite_stall = SPRITE_FETCH_DOTS;
+        }
+
+        if self.fifo.sprite_stall > 0 {
+            self.fifo.sprite_stall -= 1;
+            if self.fifo.sprite_stall == 0 { self.fetch_sprite() }
+            return false;
+        }
+
+        if !self.fifo.window && self.window_visible() && self.fifo.x + 7 >= self.wx {
+            self.fifo.window = true;
+            self.fifo.background.clear();
+            self.fifo.fetch_dot = 0;
+            self.fifo.fetch_x = 0;
+            self.fifo.discard = 0;
+        } else {
+            self.shift_pixel();
+        }
+
+        self.tick_fetcher();
+
+        let done = self.fifo.x as usize == SCREEN_WIDTH;
+        if done && self.fifo.window { self.window_line += 1 }
+        done
+    }
+
+    // -------------------------------------------------------------------------
+
+    // BG map attribute bit 3 fetches the tile from VRAM bank 1.
+    fn fetch_bank(&self) -> u8 {
+        (self.fifo.attributes >> 3) & 0x01
+    }
+
+    // Sprite pixels are only mixed into slots that are still transparent, so
+    // a sprite fetched earlier keeps priority over later ones. In CGB mode a
+    // sprite earlier in OAM takes over the slot instead.
+    fn fetch_sprite(&mut self) {
+        let sprite = self.line_sprites[self.fifo.next_sprite];
+        self.fifo.next_sprite += 1;
+
+        for column in 0..8 {
+            let position = sprite.x as i16 + column as i16 - 8 - self.fifo.x as i16;
+            if position < 0 { continue }
+
+            let position = position as usize;
+            while self.fifo.sprites.len() <= position {
+                self.fifo.sprites.push_back(TRANSPARENT);
+            }
+
+            let color = self.sprite_color(sprite, column);
+            let slot = self.fifo.sprites[position];
+            if color != 0 && (slot.color == 0 || (self.cgb && sprite.index < slot.index)) {
+                self.fifo.sprites[position] = SpritePixel {
+                    color,
+                    flags: sprite.flags,
+                    index: sprite.index,
+                };
+            }
+        }
+    }
+
+    fn fetcher_idle(&self) -> bool {
+        !self.fifo.background.is_empty()
+            && (self.fifo.fetch_dot == 0 || self.fifo.fetch_dot >= FETCH_DOTS)
+    }
+
+    fn fetcher_tile_row(&self) -> (u16, u8) {
+        let x = self.fifo.fetch_x * 8;
+
+        if self.fifo.window {
+            let map = if self.lcdc & 0x40 != 0 { 0x9C00 } else { 0x9800 };
+            self.tile_row_address(map, x, self.window_line)
+        } else {
+            let map = if self.lcdc & 0x08 != 0 { 0x9C00 } else { 0x9800 };
+            let bg_x = (self.scx & 0xF8).wrapping_add(x);
+            self.tile_row_address(map, bg_x, self.ly.wrapping_add(self.scy))
+        }
+    }
+
+    // Pops a background pixel, and any sprite pixel above it, and draws it
+    // with the palettes as they are on this dot.
+    fn shift_pixel(&mut self) {
+        let background = match self.fifo.background.pop_front() {
+            Some(pixel) => pixel,
+            None => return,
+        };
+
+        if self.fifo.discard > 0 {
+            self.fifo.discard -= 1;
+            return;
+        }
+
+        let sprite = self.fifo.sprites.pop_front().unwrap_or(TRANSPARENT);
+        let sprite = if self.lcdc & 0x02 != 0 { Some((sprite.color, sprite.flags)) } else { None };
+        let pixel = self.mix_pixel(background.color, background.attributes, sprite);
+
+        let index = self.ly as usize * SCREEN_WIDTH + self.fifo.x as usize;
+        self.frame_buffer[index] = pixel;
+        self.fifo.x += 1;
+    }
+
+    fn sprite_due(&self) -> bool {
+        self.lcdc & 0x02 != 0
+            && self.fifo.discard == 0
+            && self.fifo.next_sprite < self.line_sprites.len()
+            && self.line_sprites[self.fifo.next_sprite].x <= self.fifo.x + 8
+    }
+
+    // The fetcher reads the tile number, then the low and high bitplanes, and
+    // pushes the row once the background FIFO has emptied.
+    fn tick_fetcher(&mut self) {
+        if self.fifo.fetch_dot < FETCH_DOTS {
+            self.fifo.fetch_dot += 1;
+            match self.fifo.fetch_dot {
+                2 => {
+                    let (tile_row, attributes) = self.fetcher_tile_row();
+                    self.fifo.tile_row = tile_row;
+                    self.fifo.attributes = attributes;
+                },
+                4 => self.fifo.low = self.vram_byte(self.fetch_bank(), self.fifo.tile_row),
+                6 => self.fifo.high = self.vram_byte(self.fetch_bank(), self.fifo.tile_row + 1),
+                _ => (),
+            }
+        }
+
+        if self.fifo.fetch_dot < FETCH_DOTS || !self.fifo.background.is_empty() { return }
+
+        self.fifo.fetch_dot = 0;
+        if self.fifo.first_fetch {
+            self.fifo.first_fetch = false;
+            return;
+        }
+
+        let attributes = self.fifo.attributes;
+        let (low, high) = if attributes & 0x20 != 0 {
+            (self.fifo.low.reverse_bits(), self.fifo.high.reverse_bits())
+        } else {
+            (self.fifo.low, self.fifo.high)
+        };
+
+        for bit in (0..8).rev() {
+            let color = tile_color(low, high, bit);
+            self.fifo.background.push_back(BackgroundPixel { color, attributes });
+        }
+        self.fifo.fetch_x += 1;
+    }
+}
@@ -0,0 +1,709 @@
+// http://gbdev.gg8.se/wiki/articles/Video_Display
+mod fifo;
+
+use self::fifo::PixelFifo;
+use interrupts::{Interrupt, Interrupts};
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+const DOTS_PER_LINE: u16 = 456;
+const OAM_SCAN_DOTS: u16 = 80;
+const PIXEL_TRANSFER_DOTS: u16 = 172;
+const LINES_PER_FRAME: u8 = 154;
+
+const SPRITES_PER_LINE: usize = 10;
+
+// A frame takes the same time whether or not the LCD is on.
+pub const DOTS_PER_FRAME: u32 = DOTS_PER_LINE as u32 * LINES_PER_FRAME as u32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode { HBlank, VBlank, OamScan, PixelTransfer }
+
+impl Mode {
+    fn bits(self) -> u8 {
+        match self {
+            Mode::HBlank        => 0,
+            Mode::VBlank        => 1,
+            Mode::OamScan       => 2,
+            Mode::PixelTransfer => 3,
+        }
+    }
+}
+
+// The scanline renderer draws each line in one go at the end of a fixed
+// length mode 3. The pixel FIFO renderer draws a pixel per dot, so mode 3
+// varies in length and mid-line register writes show up on screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Renderer { Scanline, PixelFifo }
+
+#[derive(Clone, Copy)]
+struct Sprite {
+    y: u8,
+    x: u8,
+    tile: u8,
+    flags: u8,
+    index: u8,
+}
+
+impl Sprite {
+    fn y_flip(&self)   -> bool { self.flags & 0x40 != 0 }
+    fn x_flip(&self)   -> bool { self.flags & 0x20 != 0 }
+    fn vram_bank(&self) -> u8  { (self.flags >> 3) & 0x01 }
+}
+
+pub struct Ppu {
+    renderer: Renderer,
+    fifo: PixelFifo,
+    cgb: bool,
+    vram: [u8; 0x4000],
+    vram_bank: u8,
+    oam: [u8; 0xA0],
+    lcdc: u8,
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    obp0: u8,
+    obp1: u8,
+    wy: u8,
+    wx: u8,
+    bcps: u8,
+    ocps: u8,
+    bg_palettes: [u8; 0x40],
+    obj_palettes: [u8; 0x40],
+    mode: Mode,
+    dot: u16,
+    window_line: u8,
+    line_sprites: Vec<Sprite>,
+    stat_line: bool,
+    frame_buffer: Vec<u16>,
+    frames: u64,
+}
+
+impl Ppu {
+    pub fn new() -> Ppu {
+        Ppu::with_renderer(Renderer::Scanline)
+    }
+
+    pub fn with_renderer(renderer: Renderer) -> Ppu {
+        Ppu {
+            renderer,
+            fifo: PixelFifo::new(),
+            cgb: false,
+            vram: [0; 0x4000],
+            vram_bank: 0,
+            oam: [0; 0xA0],
+            lcdc: 0,
+            stat: 0,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            bgp: 0,
+            obp0: 0,
+            obp1: 0,
+            wy: 0,
+            wx: 0,
+            bcps: 0,
+            ocps: 0,
+            bg_palettes: [0xFF; 0x40],
+            obj_palettes: [0xFF; 0x40],
+            mode: Mode::HBlank,
+            dot: 0,
+            window_line: 0,
+            line_sprites: Vec::with_capacity(SPRITES_PER_LINE),
+            stat_line: false,
+            frame_buffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            frames: 0,
+        }
+    }
+
+    // One value per pixel, row by row: a shade from 0 (white) to 3 (black)
+    // on DMG, or a 15-bit BGR colour in CGB mode.
+    pub fn frame_buffer(&self) -> &[u16] {
+        &self.frame_buffer
+    }
+
+    pub fn cgb_mode(&self) -> bool {
+        self.cgb
+    }
+
+    // CGB mode adds a second VRAM bank, colour palettes and BG map
+    // attributes.
+    pub fn set_cgb_mode(&mut self, enabled: bool) {
+        self.cgb = enabled;
+    }
+
+    // Completed frames since power on, so frontends can tell when the frame
+    // buffer holds a new picture.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn renderer(&self) -> Renderer {
+        self.renderer
+    }
+
+    pub fn read_vram(&self, address: u16) -> u8 {
+        self.vram_byte(self.vram_bank, address)
+    }
+
+    pub fn write_vram(&mut self, address: u16, value: u8) {
+        self.vram[self.vram_bank as usize * 0x2000 + address as usize - 0x8000] = value;
+    }
+
+    pub fn read_oam(&self, address: u16) -> u8 {
+        self.oam[address as usize - 0xFE00]
+    }
+
+    pub fn write_oam(&mut self, address: u16, value: u8) {
+        self.oam[address as usize - 0xFE00] = value;
+    }
+
+    pub fn read(&self, address: u16) -> u8 {
+        match address {
+            0xFF40 => self.lcdc,
+            0xFF41 => self.read_stat(),
+            0xFF42 => self.scy,
+            0xFF43 => self.scx,
+            0xFF44 => self.ly,
+            0xFF45 => self.lyc,
+            0xFF47 => self.bgp,
+            0xFF48 => self.obp0,
+            0xFF49 => self.obp1,
+            0xFF4A => self.wy,
+            0xFF4B => self.wx,
+            0xFF4F if self.cgb => 0xFE | self.vram_bank,
+            0xFF68 if self.cgb => self.bcps | 0x40,
+            0xFF69 if self.cgb => self.bg_palettes[(self.bcps & 0x3F) as usize],
+            0xFF6A if self.cgb => self.ocps | 0x40,
+            0xFF6B if self.cgb => self.obj_palettes[(self.ocps & 0x3F) as usize],
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8, interrupts: &mut Interrupts) {
+        match address {
+            0xFF40 => self.write_lcdc(value),
+            0xFF41 => self.stat = value & 0x78,
+            0xFF42 => self.scy = value,
+            0xFF43 => self.scx = value,
+            0xFF45 => self.lyc = value,
+            0xFF47 => self.bgp = value,
+            0xFF48 => self.obp0 = value,
+            0xFF49 => self.obp1 = value,
+            0xFF4A => self.wy = value,
+            0xFF4B => self.wx = value,
+            0xFF4F if self.cgb => self.vram_bank = value & 0x01,
+            0xFF68 if self.cgb => self.bcps = value & 0xBF,
+            0xFF69 if self.cgb => {
+                self.bg_palettes[(self.bcps & 0x3F) as usize] = value;
+                self.bcps = increment_palette_index(self.bcps);
+            },
+            0xFF6A if self.cgb => self.ocps = value & 0xBF,
+            0xFF6B if self.cgb => {
+                self.obj_palettes[(self.ocps & 0x3F) as usize] = value;
+                self.ocps = increment_palette_index(self.ocps);
+            },
+            _ => (),
+        }
+
+        self.update_stat_line(interrupts);
+    }
+
+    // Advances the PPU by a number of dots; there are four per machine cycle
+    // at normal speed and two in double speed mode.
+    pub fn tick(&mut self, dots: u32, interrupts: &mut Interrupts) {
+        if !self.lcd_enabled() { return }
+
+        for _ in 0..dots {
+            self.tick_dot(interrupts);
+        }
+    }
+
+    // -------------------------------------------------------------------------
+
+    fn tick_dot(&mut self, interrupts: &mut Interrupts) {
+        self.dot += 1;
+
+        if self.ly < SCREEN_HEIGHT as u8 {
+            if self.mode == Mode::OamScan && self.dot == OAM_SCAN_DOTS {
+                self.scan_oam();
+                if self.renderer == Renderer::PixelFifo { self.start_pixel_fifo() }
+                self.mode = Mode::PixelTransfer;
+            } else if self.mode == Mode::PixelTransfer && self.pixel_transfer_done() {
+                self.mode = Mode::HBlank;
+            }
+        }
+
+        if self.dot == DOTS_PER_LINE {
+            self.dot = 0;
+            self.ly += 1;
+
+            if self.ly == SCREEN_HEIGHT as u8 {
+                self.mode = Mode::VBlank;
+                self.frames += 1;
+                interrupts.request(Interrupt::VBlank);
+            } else if self.ly == LINES_PER_FRAME {
+                self.ly = 0;
+                self.window_line = 0;
+                self.mode = Mode::OamScan;
+            } else if self.ly < SCREEN_HEIGHT as u8 {
+                self.mode = Mode::OamScan;
+            }
+        }
+
+        self.update_stat_line(interrupts);
+    }
+
+    // The STAT interrupt fires on a rising edge of the OR of every enabled
+    // source, so overlapping sources only raise it once.
+    fn update_stat_line(&mut self, interrupts: &mut Interrupts) {
+        let line = self.lcd_enabled() && (
+            (self.stat & 0x40 != 0 && self.ly == self.lyc) ||
+            (self.stat & 0x20 != 0 && self.mode == Mode::OamScan) ||
+            (self.stat & 0x10 != 0 && self.mode == Mode::VBlank) ||
+            (self.stat & 0x08 != 0 && self.mode == Mode::HBlank));
+
+        if line && !self.stat_line {
+            interrupts.request(Interrupt::LcdStat);
+        }
+        self.stat_line = line;
+    }
+
+    fn read_stat(&self) -> u8 {
+        let coincidence = if self.ly == self.lyc { 0x04 } else { 0 };
+        let mode = if self.lcd_enabled() { self.mode.bits() } else { 0 };
+        0x80 | self.stat | coincidence | mode
+    }
+
+    // Turning the LCD off resets LY and leaves the PPU idle in HBlank; it
+    // starts again from the top of the frame when turned back on.
+    fn write_lcdc(&mut self, value: u8) {
+        let was_enabled = self.lcd_enabled();
+        self.lcdc = value;
+
+        if was_enabled && !self.lcd_enabled() {
+            self.ly = 0;
+            self.dot = 0;
+            self.window_line = 0;
+            self.mode = Mode::HBlank;
+        } else if !was_enabled && self.lcd_enabled() {
+            self.mode = Mode::OamScan;
+        }
+    }
+
+    fn lcd_enabled(&self) -> bool {
+        self.lcdc & 0x80 != 0
+    }
+
+    // -------------------------------------------------------------------------
+
+    fn pixel_transfer_done(&mut self) -> bool {
+        match self.renderer {
+            Renderer::PixelFifo => self.tick_pixel_fifo(),
+            Renderer::Scanline => {
+                let done = self.dot == OAM_SCAN_DOTS + PIXEL_TRANSFER_DOTS;
+                if done { self.render_scanline() }
+                done
+            },
+        }
+    }
+
+    fn render_scanline(&mut self) {
+        let ly = self.ly;
+        let window_enabled = self.window_visible();
+
+        let bg_map = if self.lcdc & 0x08 != 0 { 0x9C00 } else { 0x9800 };
+        let window_map = if self.lcdc & 0x40 != 0 { 0x9C00 } else { 0x9800 };
+
+        let sprites_enabled = self.lcdc & 0x02 != 0;
+        let mut window_drawn = false;
+
+        for x in 0..SCREEN_WIDTH as u8 {
+            let (bg_color, bg_attributes) = if window_enabled && x as u16 + 7 >= self.wx as u16 {
+                window_drawn = true;
+                let window_x = (x as u16 + 7 - self.wx as u16) as u8;
+                self.tile_pixel(window_map, window_x, self.window_line)
+            } else {
+                let bg_x = x.wrapping_add(self.scx);
+                let bg_y = ly.wrapping_add(self.scy);
+                self.tile_pixel(bg_map, bg_x, bg_y)
+            };
+
+            let sprite = if sprites_enabled { self.sprite_pixel(x) } else { None };
+
+            let index = ly as usize * SCREEN_WIDTH + x as usize;
+            self.frame_buffer[index] = self.mix_pixel(bg_color, bg_attributes, sprite);
+        }
+
+        if window_drawn { self.window_line += 1 }
+    }
+
+    // Chooses between the background and the sprite above it, given as a
+    // colour number and OAM flags, and looks the result up in its palette.
+    //
+    // On DMG, LCDC bit 0 blanks the background and OAM bit 7 puts a sprite
+    // behind background colours 1-3. In CGB mode LCDC bit 0 instead lets
+    // sprites cover everything when clear, and otherwise either OAM bit 7
+    // or the BG attribute bit 7 puts the background on top.
+    fn mix_pixel(&self, bg_color: u8, bg_attributes: u8, sprite: Option<(u8, u8)>) -> u16 {
+        if self.cgb {
+            let sprite_on_top = match sprite {
+                Some((color, flags)) if color != 0 => {
+                    self.lcdc & 0x01 == 0 || bg_color == 0
+                        || (bg_attributes & 0x80 == 0 && flags & 0x80 == 0)
+                },
+                _ => false,
+            };
+
+            return match sprite {
+                Some((color, flags)) if sprite_on_top =>
+                    cgb_color(&self.obj_palettes, flags & 0x07, color),
+                _ => cgb_color(&self.bg_palettes, bg_attributes & 0x07, bg_color),
+            };
+        }
+
+        let bg_color = if self.lcdc & 0x01 != 0 { bg_color } else { 0 };
+
+        let shade = match sprite {
+            Some((color, flags)) if color != 0 && !(flags & 0x80 != 0 && bg_color != 0) => {
+                let palette = if flags & 0x10 != 0 { self.obp1 } else { self.obp0 };
+                shade(palette, color)
+            },
+            _ => shade(self.bgp, bg_color),
+        };
+        shade as u16
+    }
+
+    // Selects up to ten sprites overlapping the current line, in OAM order,
+    // then sorts them by X coordinate, ties staying in OAM order. That is
+    // both the order they are fetched in and, on DMG, their priority.
+    fn scan_oam(&mut self) {
+        let height = self.sprite_height();
+        let ly = self.ly as u16 + 16;
+
+        self.line_sprites.clear();
+        for (index, entry) in self.oam.chunks(4).enumerate() {
+            let y = entry[0] as u16;
+            if ly >= y && ly < y + height as u16 {
+                self.line_sprites.push(Sprite {
+                    y: entry[0],
+                    x: entry[1],
+                    tile: entry[2],
+                    flags: entry[3],
+                    index: index as u8,
+                });
+                if self.line_sprites.len() == SPRITES_PER_LINE { break }
+            }
+        }
+
+        self.line_sprites.sort_by_key(|sprite| sprite.x);
+    }
+
+    // The colour number and flags of the highest priority sprite with a
+    // non-transparent pixel at x. In CGB mode the sprite earliest in OAM
+    // wins regardless of X.
+    fn sprite_pixel(&self, x: u8) -> Option<(u8, u8)> {
+        let screen_x = x as u16 + 8;
+        let mut found: Option<(Sprite, u8)> = None;
+
+        for &sprite in &self.line_sprites {
+            let sprite_x = sprite.x as u16;
+            if screen_x < sprite_x || screen_x >= sprite_x + 8 { continue }
+
+            let column = (screen_x - sprite_x) as u8;
+            let color = self.sprite_color(sprite, column);
+            if color == 0 { continue }
+
+            if !self.cgb { return Some((color, sprite.flags)) }
+            if found.is_none_or(|(other, _)| sprite.index < other.index) {
+                found = Some((sprite, color));
+            }
+        }
+
+        found.map(|(sprite, color)| (color, sprite.flags))
+    }
+
+    // The colour number of one column of a sprite on the current line.
+    fn sprite_color(&self, sprite: Sprite, column: u8) -> u8 {
+        let height = self.sprite_height();
+
+        // LCDC can switch to 8x8 sprites after OAM scan picked a row from the
+        // bottom half of an 8x16 one, so only the rows of the current size
+        // are addressable.
+        let mut row = (self.ly as u16 + 16 - sprite.y as u16) as u8 & (height - 1);
+        if sprite.y_flip() { row = height - 1 - row }
+
+        let column = if sprite.x_flip() { 7 - column } else { column };
+        let bank = if self.cgb { sprite.vram_bank() } else { 0 };
+
+        // 8x16 sprites use an even/odd pair of tiles.
+        let tile = if height == 16 { sprite.tile & 0xFE } else { sprite.tile };
+        let address = 0x8000 + tile as u16 * 16 + row as u16 * 2;
+        tile_color(self.vram_byte(bank, address), self.vram_byte(bank, address + 1), 7 - column)
+    }
+
+    fn sprite_height(&self) -> u8 {
+        if self.lcdc & 0x04 != 0 { 16 } else { 8 }
+    }
+
+    // The two-bit colour number of a pixel within a 256x256 tile map, and
+    // the CGB attributes of its tile.
+    fn tile_pixel(&self, map: u16, x: u8, y: u8) -> (u8, u8) {
+        let (row, attributes) = self.tile_row_address(map, x, y);
+        let bank = (attributes >> 3) & 0x01;
+
+        let column = if attributes & 0x20 != 0 { 7 - x % 8 } else { x % 8 };
+        let color = tile_color(self.vram_byte(bank, row), self.vram_byte(bank, row + 1), 7 - column);
+        (color, attributes)
+    }
+
+    // The address of the low byte of the row of the tile under a pixel
+    // within a 256x256 tile map, and the tile's attributes. In CGB mode
+    // these sit at the same map address in VRAM bank 1: bits 0-2 pick the
+    // palette, bit 3 the tile's bank, bits 5 and 6 flip it and bit 7 gives
+    // it priority over sprites.
+    fn tile_row_address(&self, map: u16, x: u8, y: u8) -> (u16, u8) {
+        let map_address = map + (y as u16 / 8) * 32 + x as u16 / 8;
+        let tile = self.vram_byte(0, map_address);
+        let attributes = if self.cgb { self.vram_byte(1, map_address) } else { 0 };
+
+        // LCDC bit 4 selects between unsigned tile numbers from 0x8000 and
+        // signed ones from 0x9000.
+        let tile_address = if self.lcdc & 0x10 != 0 {
+            0x8000 + tile as u16 * 16
+        } else {
+            0x9000u16.wrapping_add((tile as i8 as i16 * 16) as u16)
+        };
+
+        let row = if attributes & 0x40 != 0 { 7 - y % 8 } else { y % 8 };
+        (tile_address + row as u16 * 2, attributes)
+    }
+
+    // VRAM as the PPU sees it, with both banks available whatever VBK says.
+    fn vram_byte(&self, bank: u8, address: u16) -> u8 {
+        self.vram[bank as usize * 0x2000 + address as usize - 0x8000]
+    }
+
+    // Whether the window covers part of the current line. On DMG clearing
+    // LCDC bit 0 hides the window along with the background.
+    fn window_visible(&self) -> bool {
+        let enabled = if self.cgb { self.lcdc & 0x20 != 0 } else { self.lcdc & 0x21 == 0x21 };
+        enabled && self.wy <= self.ly && self.wx <= 166
+    }
+}
+
+impl Default for Ppu {
+    fn default() -> Ppu {
+        Ppu::new()
+    }
+}
+
+// A little-endian 15-bit colour from CGB palette RAM, which holds eight
+// palettes of four colours.
+fn cgb_color(palettes: &[u8; 0x40], palette: u8, color: u8) -> u16 {
+    let index = palette as usize * 8 + color as usize * 2;
+    (palettes[index] as u16 | (palettes[index + 1] as u16) << 8) & 0x7FFF
+}
+
+// BCPS/OCPS bit 7 makes the index advance after each data write.
+fn increment_palette_index(value: u8) -> u8 {
+    if value & 0x80 == 0 { return value }
+    0x80 | (value.wrapping_add(1) & 0x3F)
+}
+
+fn shade(palette: u8, color: u8) -> u8 {
+    (palette >> (color * 2)) & 0x03
+}
+
+fn tile_color(low: u8, high: u8, bit: u8) -> u8 {
+    (((high >> bit) & 0x01) << 1) | ((low >> bit) & 0x01)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A Y-flipped 8x16 sprite covering lines 0-15 at X 0, drawn with LCDC
+    // switched to 8x8 sprites at the start of mode 3 on line 12. Tile 0 has
+    // only its fourth row set, which is the row a flipped 8x8 sprite shows
+    // there.
+    fn render_with_sprite_size_change(renderer: Renderer) -> Ppu {
+        let mut ppu = Ppu::with_renderer(renderer);
+        let mut interrupts = Interrupts::new();
+
+        ppu.write_vram(0x8006, 0xFF);
+        ppu.write_oam(0xFE00, 16);
+        ppu.write_oam(0xFE01, 8);
+        ppu.write_oam(0xFE02, 0);
+        ppu.write_oam(0xFE03, 0x40);
+        ppu.write(0xFF48, 0xE4, &mut interrupts);
+        ppu.write(0xFF40, 0x86, &mut interrupts);
+
+        while !(ppu.read(0xFF44) == 12 && ppu.mode() == Mode::PixelTransfer) {
+            ppu.tick(1, &mut interrupts);
+        }
+        ppu.write(0xFF40, 0x82, &mut interrupts);
+
+        while ppu.read(0xFF44) == 12 {
+            ppu.tick(1, &mut interrupts);
+        }
+        ppu
+    }
+
+    #[test]
+    fn sprite_size_change_during_mode_3_uses_current_height() {
+        let ppu = render_with_sprite_size_change(Renderer::Scanline);
+        assert_eq!(ppu.frame_buffer()[12 * SCREEN_WIDTH], 1);
+    }
+
+    #[test]
+    fn pixel_fifo_sprite_size_change_during_mode_3_uses_current_height() {
+        let ppu = render_with_sprite_size_change(Renderer::PixelFifo);
+        assert_eq!(ppu.frame_buffer()[12 * SCREEN_WIDTH], 1);
+    }
+
+    fn tick_until_line(ppu: &mut Ppu, line: u8, interrupts: &mut Interrupts) {
+        while ppu.read(0xFF44) != line {
+            ppu.tick(1, interrupts);
+        }
+    }
+
+    #[test]
+    fn lyc_match_requests_stat_interrupt() {
+        let mut ppu = Ppu::new();
+        let mut interrupts = Interrupts::new();
+
+        ppu.write(0xFF45, 3, &mut interrupts);
+        ppu.write(0xFF41, 0x40, &mut interrupts);
+        ppu.write(0xFF40, 0x80, &mut interrupts);
+
+        tick_until_line(&mut ppu, 2, &mut interrupts);
+        assert_eq!(interrupts.read_flags() & 0x02, 0);
+        assert_eq!(ppu.read(0xFF41) & 0x04, 0);
+
+        tick_until_line(&mut ppu, 3, &mut interrupts);
+        assert_eq!(interrupts.read_flags() & 0x02, 0x02);
+        assert_eq!(ppu.read(0xFF41) & 0x04, 0x04);
+    }
+
+    #[test]
+    fn vblank_starts_at_line_144() {
+        let mut ppu = Ppu::new();
+        let mut interrupts = Interrupts::new();
+        ppu.write(0xFF40, 0x80, &mut interrupts);
+
+        tick_until_line(&mut ppu, 143, &mut interrupts);
+        assert_eq!(interrupts.read_flags() & 0x01, 0);
+
+        tick_until_line(&mut ppu, 144, &mut interrupts);
+        assert_eq!(interrupts.read_flags() & 0x01, 0x01);
+        assert_eq!(ppu.mode(), Mode::VBlank);
+        assert_eq!(ppu.frames(), 1);
+    }
+
+    #[test]
+    fn window_covers_background_from_wx_minus_7() {
+        let mut ppu = Ppu::new();
+        let mut interrupts = Interrupts::new();
+
+        // Tile 1 is solid colour 1 and fills the window map at 0x9C00; the
+        // background map at 0x9800 stays on the blank tile 0.
+        for address in (0x8010..0x8020).step_by(2) {
+            ppu.write_vram(address, 0xFF);
+        }
+        for address in 0x9C00..0xA000 {
+            ppu.write_vram(address, 0x01);
+        }
+        ppu.write(0xFF47, 0xE4, &mut interrupts);
+        ppu.write(0xFF4A, 0, &mut interrupts);
+        ppu.write(0xFF4B, 87, &mut interrupts);
+        ppu.write(0xFF40, 0xF1, &mut interrupts);
+
+        tick_until_line(&mut ppu, 1, &mut interrupts);
+        let line = &ppu.frame_buffer()[..SCREEN_WIDTH];
+        assert!(line[..80].iter().all(|&pixel| pixel == 0));
+        assert!(line[80..].iter().all(|&pixel| pixel == 1));
+    }
+
+    #[test]
+    fn palette_index_auto_increments_and_wraps() {
+        let mut ppu = Ppu::new();
+        let mut interrupts = Interrupts::new();
+        ppu.set_cgb_mode(true);
+
+        ppu.write(0xFF68, 0xBE, &mut interrupts);
+        ppu.write(0xFF69, 0x11, &mut interrupts);
+        ppu.write(0xFF69, 0x22, &mut interrupts);
+        assert_eq!(ppu.read(0xFF68), 0xC0);
+
+        ppu.write(0xFF68, 0x3E, &mut interrupts);
+        assert_eq!(ppu.read(0xFF69), 0x11);
+        ppu.write(0xFF69, 0x33, &mut interrupts);
+        assert_eq!(ppu.read(0xFF68), 0x7E);
+        assert_eq!(ppu.read(0xFF69), 0x33);
+
+        ppu.write(0xFF68, 0x3F, &mut interrupts);
+        assert_eq!(ppu.read(0xFF69), 0x22);
+
+        // Object palettes have their own index.
+        ppu.write(0xFF6A, 0x80, &mut interrupts);
+        ppu.write(0xFF6B, 0x44, &mut interrupts);
+        assert_eq!(ppu.read(0xFF6A), 0xC1);
+        assert_eq!(ppu.read(0xFF68), 0x7F);
+    }
+
+    #[test]
+    fn vbk_selects_vram_bank_in_cgb_mode_only() {
+        let mut ppu = Ppu::new();
+        let mut interrupts = Interrupts::new();
+
+        ppu.write(0xFF4F, 0x01, &mut interrupts);
+        assert_eq!(ppu.read(0xFF4F), 0xFF);
+        ppu.write_vram(0x8000, 0xAA);
+
+        ppu.set_cgb_mode(true);
+        assert_eq!(ppu.read(0xFF4F), 0xFE);
+        ppu.write(0xFF4F, 0x01, &mut interrupts);
+        assert_eq!(ppu.read(0xFF4F), 0xFF);
+        assert_eq!(ppu.read_vram(0x8000), 0x00);
+        ppu.write_vram(0x8000, 0x55);
+
+        ppu.write(0xFF4F, 0x00, &mut interrupts);
+        assert_eq!(ppu.read_vram(0x8000), 0xAA);
+    }
+
+    #[test]
+    fn bg_attributes_pick_tile_bank_and_palette() {
+        let mut ppu = Ppu::new();
+        let mut interrupts = Interrupts::new();
+        ppu.set_cgb_mode(true);
+
+        // Tile 0 in bank 1 is solid colour 3; the first map entry uses it
+        // with palette 2, whose colour 3 is pure red.
+        ppu.write(0xFF4F, 0x01, &mut interrupts);
+        for address in 0x8000..0x8010 {
+            ppu.write_vram(address, 0xFF);
+        }
+        ppu.write_vram(0x9800, 0x0A);
+        ppu.write(0xFF4F, 0x00, &mut interrupts);
+
+        ppu.write(0xFF68, 0x80 | (2 * 8 + 3 * 2), &mut interrupts);
+        ppu.write(0xFF69, 0x1F, &mut interrupts);
+        ppu.write(0xFF69, 0x00, &mut interrupts);
+        ppu.write(0xFF40, 0x91, &mut interrupts);
+
+        tick_until_line(&mut ppu, 1, &mut interrupts);
+        let line = &ppu.frame_buffer()[..SCREEN_WIDTH];
+        assert!(line[..8].iter().all(|&pixel| pixel == 0x001F));
+        assert!(line[8..].iter().all(|&pixel| pixel == 0x7FFF));
+    }
+}
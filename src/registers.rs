@@ -51,6 +51,12 @@ impl Registers {
     }
 }
 
+impl Default for Registers {
+    fn default() -> Registers {
+        Registers::new()
+    }
+}
+
 impl Index<Register> for Registers {
     type Output = u8;
 
@@ -0,0 +1,61 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+// A .sav file next to the ROM holding battery-backed cartridge RAM. The
+// contents are whatever the mapper reports, so they match the raw dumps other
+// emulators read and write.
+pub struct SaveFile {
+    path: PathBuf,
+    dirty: bool,
+    last_write: Instant,
+}
+
+impl SaveFile {
+    pub fn for_rom(rom_path: &Path) -> SaveFile {
+        SaveFile {
+            path: rom_path.with_extension("sav"),
+            dirty: false,
+            last_write: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn read(&self) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(&self.path) {
+            Ok(data) => Ok(Some(data)),
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    // Writes go to a temporary file first so that a crash part way through
+    // never leaves a truncated save behind.
+    pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let temporary_path = self.path.with_extension("sav.tmp");
+        fs::write(&temporary_path, data)?;
+        fs::rename(&temporary_path, &self.path)?;
+
+        self.dirty = false;
+        self.last_write = Instant::now();
+        Ok(())
+    }
+
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.dirty && self.last_write.elapsed() >= AUTOSAVE_INTERVAL
+    }
+}
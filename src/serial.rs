@@ -0,0 +1,331 @@
+// http://gbdev.gg8.se/wiki/articles/Serial_Data_Transfer_(Link_Cable)
+use interrupts::{Interrupt, Interrupts};
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+// With the internal clock a byte is shifted out at 8192 Hz, one bit every
+// 128 machine cycles.
+const TRANSFER_CYCLES: u16 = 8 * 128;
+
+// Whatever is plugged into the other end of the link cable.
+pub trait SerialLink {
+    // Called when this side has clocked out a byte; returns the byte that
+    // was shifted in from the other end at the same time.
+    fn exchange(&mut self, byte: u8) -> u8;
+
+    // Polled while this side waits on an external clock. Returns the byte
+    // received once the other end has driven a transfer, having sent it
+    // the byte given.
+    fn poll(&mut self, _byte: u8) -> Option<u8> { None }
+
+    // Called when this side stops waiting on an external clock without a
+    // transfer having completed.
+    fn cancel(&mut self) {}
+}
+
+// No cable: the input line floats high and no external clock ever arrives.
+pub struct DisconnectedLink;
+
+impl SerialLink for DisconnectedLink {
+    fn exchange(&mut self, _byte: u8) -> u8 { 0xFF }
+}
+
+// Prints each byte as it is sent, which is how test ROMs report results.
+pub struct StdoutLink;
+
+impl SerialLink for StdoutLink {
+    fn exchange(&mut self, byte: u8) -> u8 {
+        print!("{}", byte as char);
+        let _ = io::stdout().flush();
+        0xFF
+    }
+}
+
+// Collects sent bytes into a string that stays readable through output()
+// after the link has been handed to the emulator.
+pub struct CaptureLink {
+    output: Rc<RefCell<String>>,
+}
+
+impl CaptureLink {
+    pub fn new() -> CaptureLink {
+        CaptureLink { output: Rc::new(RefCell::new(String::new())) }
+    }
+
+    pub fn output(&self) -> Rc<RefCell<String>> {
+        self.output.clone()
+    }
+}
+
+impl Default for CaptureLink {
+    fn default() -> CaptureLink {
+        CaptureLink::new()
+    }
+}
+
+impl SerialLink for CaptureLink {
+    fn exchange(&mut self, byte: u8) -> u8 {
+        self.output.borrow_mut().push(byte as char);
+        0xFF
+    }
+}
+
+// Two emulator cores in the same process, stepped in turn, joined by a link
+// cable. Each end publishes its SB while it waits on an external clock, and
+// a transfer clocked by the other end swaps the two bytes. Clocking a byte
+// out while the other side is not waiting shifts in 0xFF, as if unplugged.
+struct Cable {
+    waiting: [Option<u8>; 2],
+    delivered: [Option<u8>; 2],
+}
+
+pub struct CableLink {
+    cable: Rc<RefCell<Cable>>,
+    end: usize,
+}
+
+pub fn link_cable() -> (CableLink, CableLink) {
+    let cable = Rc::new(RefCell::new(Cable {
+        waiting: [None, None],
+        delivered: [None, None],
+    }));
+
+    (CableLink { cable: cable.clone(), end: 0 }, CableLink { cable, end: 1 })
+}
+
+impl SerialLink for CableLink {
+    fn exchange(&mut self, byte: u8) -> u8 {
+        let mut cable = self.cable.borrow_mut();
+        let peer = 1 - self.end;
+
+        match cable.waiting[peer].take() {
+            Some(received) => {
+                cable.delivered[peer] = Some(byte);
+                received
+            },
+            None => 0xFF,
+        }
+    }
+
+    fn poll(&mut self, byte: u8) -> Option<u8> {
+        let mut cable = self.cable.borrow_mut();
+
+        let received = cable.delivered[self.end].take();
+        if received.is_none() { cable.waiting[self.end] = Some(byte) }
+        received
+    }
+
+    fn cancel(&mut self) {
+        let mut cable = self.cable.borrow_mut();
+        cable.waiting[self.end] = None;
+        cable.delivered[self.end] = None;
+    }
+}
+
+// SB (0xFF01) holds the byte being shifted; SC (0xFF02) bit 7 starts a
+// transfer and stays set until it completes, bit 0 selects the internal
+// clock.
+pub struct Serial {
+    link: Box<dyn SerialLink>,
+    sb: u8,
+    sc: u8,
+    remaining_cycles: u16,
+}
+
+impl Serial {
+    pub fn new() -> Serial {
+        Serial {
+            link: Box::new(DisconnectedLink),
+            sb: 0,
+            sc: 0,
+            remaining_cycles: 0,
+        }
+    }
+
+    pub fn set_link(&mut self, link: Box<dyn SerialLink>) {
+        self.link = link;
+    }
+
+    pub fn read(&self, address: u16) -> u8 {
+        match address {
+            0xFF01 => self.sb,
+            _ => self.sc | 0x7E,
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0xFF01 => self.sb = value,
+            _ => {
+                let was_waiting = self.waiting_for_clock();
+                self.sc = value & 0x81;
+                if self.transferring() && self.internal_clock() {
+                    self.remaining_cycles = TRANSFER_CYCLES;
+                }
+                if was_waiting && !self.waiting_for_clock() { self.link.cancel() }
+            },
+        }
+    }
+
+    // Called once per machine cycle.
+    pub fn tick(&mut self, interrupts: &mut Interrupts) {
+        if !self.transferring() { return }
+
+        let received = if self.internal_clock() {
+            self.remaining_cycles -= 1;
+            if self.remaining_cycles > 0 { return }
+            self.link.exchange(self.sb)
+        } else {
+            match self.link.poll(self.sb) {
+                Some(byte) => byte,
+                None => return,
+            }
+        };
+
+        self.sb = received;
+        self.sc &= 0x7F;
+        interrupts.request(Interrupt::Serial);
+    }
+
+    // -------------------------------------------------------------------------
+
+    fn internal_clock(&self) -> bool {
+        self.sc & 0x01 != 0
+    }
+
+    fn transferring(&self) -> bool {
+        self.sc & 0x80 != 0
+    }
+
+    fn waiting_for_clock(&self) -> bool {
+        self.transferring() && !self.internal_clock()
+    }
+}
+
+impl Default for Serial {
+    fn default() -> Serial {
+        Serial::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn internal_clock_transfer_completes_after_1024_cycles() {
+        let link = CaptureLink::new();
+        let output = link.output();
+        let mut serial = Serial::new();
+        let mut interrupts = Interrupts::new();
+        serial.set_link(Box::new(link));
+
+        serial.write(0xFF01, b'P');
+        serial.write(0xFF02, 0x81);
+        for _ in 0..TRANSFER_CYCLES - 1 {
+            serial.tick(&mut interrupts);
+        }
+        assert_eq!(serial.read(0xFF02), 0xFF);
+        assert_eq!(interrupts.read_flags() & 0x08, 0);
+
+        serial.tick(&mut interrupts);
+        assert_eq!(serial.read(0xFF02), 0x7F);
+        assert_eq!(serial.read(0xFF01), 0xFF);
+        assert_eq!(interrupts.read_flags() & 0x08, 0x08);
+        assert_eq!(*output.borrow(), "P");
+    }
+
+    #[test]
+    fn external_clock_without_cable_never_completes() {
+        let mut serial = Serial::new();
+        let mut interrupts = Interrupts::new();
+
+        serial.write(0xFF01, 0x42);
+        serial.write(0xFF02, 0x80);
+        for _ in 0..4 * TRANSFER_CYCLES {
+            serial.tick(&mut interrupts);
+        }
+        assert_eq!(serial.read(0xFF02), 0xFE);
+        assert_eq!(serial.read(0xFF01), 0x42);
+        assert_eq!(interrupts.read_flags() & 0x08, 0);
+    }
+
+    // Two ports joined by a cable. The first one acts as the master in
+    // these tests and the second waits on its clock.
+    fn linked_pair() -> (Serial, Serial) {
+        let (first, second) = link_cable();
+        let mut master = Serial::new();
+        let mut slave = Serial::new();
+        master.set_link(Box::new(first));
+        slave.set_link(Box::new(second));
+        (master, slave)
+    }
+
+    fn tick_pair(pair: &mut (Serial, Serial), interrupts: &mut [Interrupts; 2], cycles: u16) {
+        for _ in 0..cycles {
+            pair.0.tick(&mut interrupts[0]);
+            pair.1.tick(&mut interrupts[1]);
+        }
+    }
+
+    #[test]
+    fn cable_swaps_bytes_once_the_master_clocks() {
+        let mut pair = linked_pair();
+        let mut interrupts = [Interrupts::new(), Interrupts::new()];
+
+        pair.1.write(0xFF01, 0x22);
+        pair.1.write(0xFF02, 0x80);
+        tick_pair(&mut pair, &mut interrupts, 3 * TRANSFER_CYCLES);
+        assert_eq!(pair.1.read(0xFF02), 0xFE);
+        assert_eq!(interrupts[1].read_flags() & 0x08, 0);
+
+        pair.0.write(0xFF01, 0x11);
+        pair.0.write(0xFF02, 0x81);
+        tick_pair(&mut pair, &mut interrupts, TRANSFER_CYCLES - 1);
+        assert_eq!(pair.1.read(0xFF02), 0xFE);
+
+        tick_pair(&mut pair, &mut interrupts, 1);
+        assert_eq!(pair.0.read(0xFF01), 0x22);
+        assert_eq!(pair.1.read(0xFF01), 0x11);
+        assert_eq!(pair.0.read(0xFF02), 0x7F);
+        assert_eq!(pair.1.read(0xFF02), 0x7E);
+        assert_eq!(interrupts[0].read_flags() & 0x08, 0x08);
+        assert_eq!(interrupts[1].read_flags() & 0x08, 0x08);
+    }
+
+    #[test]
+    fn cable_master_without_waiting_peer_shifts_in_ff() {
+        let mut pair = linked_pair();
+        let mut interrupts = [Interrupts::new(), Interrupts::new()];
+
+        pair.1.write(0xFF01, 0x22);
+        pair.0.write(0xFF01, 0x11);
+        pair.0.write(0xFF02, 0x81);
+        tick_pair(&mut pair, &mut interrupts, TRANSFER_CYCLES);
+
+        assert_eq!(pair.0.read(0xFF01), 0xFF);
+        assert_eq!(interrupts[0].read_flags() & 0x08, 0x08);
+        assert_eq!(pair.1.read(0xFF01), 0x22);
+        assert_eq!(interrupts[1].read_flags() & 0x08, 0);
+    }
+
+    #[test]
+    fn cable_slave_cancelling_stops_waiting() {
+        let mut pair = linked_pair();
+        let mut interrupts = [Interrupts::new(), Interrupts::new()];
+
+        pair.1.write(0xFF01, 0x22);
+        pair.1.write(0xFF02, 0x80);
+        tick_pair(&mut pair, &mut interrupts, 1);
+        pair.1.write(0xFF02, 0x00);
+
+        pair.0.write(0xFF01, 0x11);
+        pair.0.write(0xFF02, 0x81);
+        tick_pair(&mut pair, &mut interrupts, TRANSFER_CYCLES);
+
+        assert_eq!(pair.0.read(0xFF01), 0xFF);
+        assert_eq!(pair.1.read(0xFF01), 0x22);
+        assert_eq!(interrupts[1].read_flags() & 0x08, 0);
+    }
+}
@@ -0,0 +1,259 @@
+// http://gbdev.gg8.se/wiki/articles/Timer_Obscure_Behaviour
+use interrupts::{Interrupt, Interrupts};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Overflow { None, Pending, Reloading }
+
+// DIV is the upper byte of a 16-bit counter that advances every T-cycle.
+// TIMA increments on a falling edge of one of its bits, selected by TAC and
+// gated by the TAC enable bit, so resetting DIV or changing TAC can produce
+// an edge and an extra increment.
+pub struct Timer {
+    divider: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    overflow: Overflow,
+}
+
+impl Timer {
+    pub fn new() -> Timer {
+        Timer {
+            divider: 0,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+            overflow: Overflow::None,
+        }
+    }
+
+    pub fn read(&self, address: u16) -> u8 {
+        match address {
+            0xFF04 => (self.divider >> 8) as u8,
+            0xFF05 => self.tima,
+            0xFF06 => self.tma,
+            _ => self.tac | 0xF8,
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0xFF04 => {
+                let old_signal = self.signal();
+                self.divider = 0;
+                self.detect_falling_edge(old_signal);
+            },
+
+            // Writing TIMA in the cycle after an overflow cancels the reload,
+            // but in the cycle where TMA is being copied it has no effect.
+            0xFF05 => match self.overflow {
+                Overflow::Reloading => (),
+                _ => {
+                    self.tima = value;
+                    self.overflow = Overflow::None;
+                },
+            },
+
+            0xFF06 => {
+                self.tma = value;
+                if self.overflow == Overflow::Reloading { self.tima = value }
+            },
+
+            _ => {
+                let old_signal = self.signal();
+                self.tac = value & 0x07;
+                self.detect_falling_edge(old_signal);
+            },
+        }
+    }
+
+    // Sets the internal counter directly, without the falling edge a DIV
+    // write can cause.
+    pub fn set_divider(&mut self, divider: u16) {
+        self.divider = divider;
+    }
+
+    // Advances the timer by one machine cycle.
+    pub fn tick(&mut self, interrupts: &mut Interrupts) {
+        match self.overflow {
+            Overflow::Pending => {
+                self.tima = self.tma;
+                self.overflow = Overflow::Reloading;
+                interrupts.request(Interrupt::Timer);
+            },
+            Overflow::Reloading => self.overflow = Overflow::None,
+            Overflow::None => (),
+        }
+
+        let old_signal = self.signal();
+        self.divider = self.divider.wrapping_add(4);
+        self.detect_falling_edge(old_signal);
+    }
+
+    // -------------------------------------------------------------------------
+
+    fn detect_falling_edge(&mut self, old_signal: bool) {
+        if old_signal && !self.signal() {
+            self.increment();
+        }
+    }
+
+    // TIMA reads as zero for one cycle before TMA is reloaded into it.
+    fn increment(&mut self) {
+        let (tima, overflowed) = self.tima.overflowing_add(1);
+        self.tima = tima;
+        if overflowed { self.overflow = Overflow::Pending }
+    }
+
+    fn signal(&self) -> bool {
+        let bit = match self.tac & 0x03 {
+            0x00 => 9,
+            0x01 => 3,
+            0x02 => 5,
+            _ => 7,
+        };
+
+        self.tac & 0x04 == 0x04 && self.divider & (1 << bit) != 0
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Timer {
+        Timer::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMER_FLAG: u8 = 0x04;
+
+    // TAC 0x05 counts falling edges of divider bit 3, every 16 T-cycles.
+    fn fast_timer() -> Timer {
+        let mut timer = Timer::new();
+        timer.write(0xFF07, 0x05);
+        timer
+    }
+
+    // A timer that has just overflowed from 0xFF, with TMA set to 0x20.
+    fn overflowed_timer() -> Timer {
+        let mut timer = fast_timer();
+        timer.write(0xFF06, 0x20);
+        timer.write(0xFF05, 0xFF);
+        timer.set_divider(0x0008);
+        timer.write(0xFF04, 0x00);
+        timer
+    }
+
+    #[test]
+    fn tima_counts_at_selected_rate() {
+        let mut timer = fast_timer();
+        let mut interrupts = Interrupts::new();
+
+        for _ in 0..4 { timer.tick(&mut interrupts) }
+        assert_eq!(timer.read(0xFF05), 1);
+
+        for _ in 0..4 { timer.tick(&mut interrupts) }
+        assert_eq!(timer.read(0xFF05), 2);
+    }
+
+    #[test]
+    fn div_write_resets_divider() {
+        let mut timer = Timer::new();
+        timer.set_divider(0xABCC);
+        assert_eq!(timer.read(0xFF04), 0xAB);
+
+        timer.write(0xFF04, 0x12);
+        assert_eq!(timer.read(0xFF04), 0x00);
+    }
+
+    #[test]
+    fn div_write_with_selected_bit_high_increments_tima() {
+        let mut timer = fast_timer();
+        timer.set_divider(0x0008);
+        timer.write(0xFF04, 0x00);
+        assert_eq!(timer.read(0xFF05), 1);
+    }
+
+    #[test]
+    fn div_write_with_selected_bit_low_leaves_tima() {
+        let mut timer = fast_timer();
+        timer.set_divider(0x0010);
+        timer.write(0xFF04, 0x00);
+        assert_eq!(timer.read(0xFF05), 0);
+    }
+
+    #[test]
+    fn disabling_timer_with_selected_bit_high_increments_tima() {
+        let mut timer = fast_timer();
+        timer.set_divider(0x0008);
+        timer.write(0xFF07, 0x01);
+        assert_eq!(timer.read(0xFF05), 1);
+    }
+
+    #[test]
+    fn changing_to_a_low_bit_increments_tima() {
+        let mut timer = fast_timer();
+        timer.set_divider(0x0008);
+        timer.write(0xFF07, 0x04);
+        assert_eq!(timer.read(0xFF05), 1);
+    }
+
+    #[test]
+    fn enabling_timer_does_not_increment_tima() {
+        let mut timer = Timer::new();
+        timer.set_divider(0x0008);
+        timer.write(0xFF07, 0x05);
+        assert_eq!(timer.read(0xFF05), 0);
+    }
+
+    #[test]
+    fn overflow_reads_zero_for_a_cycle_before_reload_and_interrupt() {
+        let mut timer = overflowed_timer();
+        let mut interrupts = Interrupts::new();
+        assert_eq!(timer.read(0xFF05), 0x00);
+
+        timer.tick(&mut interrupts);
+        assert_eq!(timer.read(0xFF05), 0x20);
+        assert_eq!(interrupts.read_flags() & TIMER_FLAG, TIMER_FLAG);
+    }
+
+    #[test]
+    fn tima_write_before_reload_cancels_it() {
+        let mut timer = overflowed_timer();
+        let mut interrupts = Interrupts::new();
+
+        timer.write(0xFF05, 0x50);
+        timer.tick(&mut interrupts);
+        assert_eq!(timer.read(0xFF05), 0x50);
+        assert_eq!(interrupts.read_flags() & TIMER_FLAG, 0);
+    }
+
+    #[test]
+    fn tima_write_during_reload_is_ignored() {
+        let mut timer = overflowed_timer();
+        let mut interrupts = Interrupts::new();
+
+        timer.tick(&mut interrupts);
+        timer.write(0xFF05, 0x50);
+        assert_eq!(timer.read(0xFF05), 0x20);
+    }
+
+    #[test]
+    fn tma_write_during_reload_also_reaches_tima() {
+        let mut timer = overflowed_timer();
+        let mut interrupts = Interrupts::new();
+
+        timer.tick(&mut interrupts);
+        timer.write(0xFF06, 0x33);
+        assert_eq!(timer.read(0xFF05), 0x33);
+
+        // A cycle later TIMA is writable again and TMA no longer copies.
+        timer.tick(&mut interrupts);
+        timer.write(0xFF06, 0x44);
+        assert_eq!(timer.read(0xFF05), 0x33);
+        timer.write(0xFF05, 0x55);
+        assert_eq!(timer.read(0xFF05), 0x55);
+    }
+}
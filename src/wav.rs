@@ -0,0 +1,86 @@
+// http://soundfile.sapp.org/doc/WaveFormat/
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// Writes 16-bit PCM samples, interleaved if there is more than one channel,
+// as a canonical 44-byte header WAV file.
+pub fn write<W: Write>(writer: &mut W,
+                       sample_rate: u32,
+                       channels: u16,
+                       samples: &[i16]) -> io::Result<()> {
+
+    let block_align = channels * 2;
+    let data_size = samples.len() as u32 * 2;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&channels.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&16u16.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    for sample in samples {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+
+    writer.flush()
+}
+
+pub fn save(path: &Path, sample_rate: u32, channels: u16, samples: &[i16]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write(&mut writer, sample_rate, channels, samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+    }
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+    }
+
+    #[test]
+    fn writes_stereo_header_and_samples() {
+        let mut bytes = Vec::new();
+        write(&mut bytes, 44_100, 2, &[1, -1, 0x1234, -0x8000]).unwrap();
+
+        assert_eq!(bytes.len(), 44 + 8);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32_at(&bytes, 4), 36 + 8);
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(u32_at(&bytes, 16), 16);
+        assert_eq!(u16_at(&bytes, 20), 1);
+        assert_eq!(u16_at(&bytes, 22), 2);
+        assert_eq!(u32_at(&bytes, 24), 44_100);
+        assert_eq!(u32_at(&bytes, 28), 44_100 * 4);
+        assert_eq!(u16_at(&bytes, 32), 4);
+        assert_eq!(u16_at(&bytes, 34), 16);
+        assert_eq!(&bytes[36..40], b"data");
+        assert_eq!(u32_at(&bytes, 40), 8);
+        assert_eq!(&bytes[44..], &[0x01, 0x00, 0xFF, 0xFF, 0x34, 0x12, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn mono_block_align_is_one_sample() {
+        let mut bytes = Vec::new();
+        write(&mut bytes, 22_050, 1, &[0; 3]).unwrap();
+
+        assert_eq!(u32_at(&bytes, 4), 36 + 6);
+        assert_eq!(u32_at(&bytes, 28), 22_050 * 2);
+        assert_eq!(u16_at(&bytes, 32), 2);
+        assert_eq!(u32_at(&bytes, 40), 6);
+    }
+}